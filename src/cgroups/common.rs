@@ -1,9 +1,14 @@
-use crate::oci::oci::LinuxResources;
-use anyhow::Result;
+use crate::oci::oci::{LinuxBlockIoDevice, LinuxDeviceCgroup, LinuxResources};
+use anyhow::{bail, Context, Result};
+use nix::sys::stat::{major, minor, stat, SFlag};
+use nix::unistd::Pid;
+use std::collections::BTreeSet;
 use std::fs;
 use std::io::Write;
 use std::path::Path;
 
+pub const CGROUP_PROCS: &str = "cgroup.procs";
+
 #[derive(Clone, Debug)]
 pub struct ControllerOpt<'a> {
     pub resources: &'a LinuxResources,
@@ -31,3 +36,142 @@ pub fn write_cgroup_file<P: AsRef<Path>, T: ToString>(path: P, data: T) -> Resul
 
     Ok(())
 }
+
+//读取cgroup及其所有子cgroup中的进程
+pub fn get_all_pids(path: &Path) -> Result<Vec<Pid>> {
+    let mut pids = Vec::new();
+    let procs = fs::read_to_string(path.join(CGROUP_PROCS))
+        .with_context(|| format!("failed to read {:?}", path.join(CGROUP_PROCS)))?;
+    for line in procs.lines() {
+        pids.push(Pid::from_raw(line.trim().parse()?));
+    }
+    for entry in fs::read_dir(path)? {
+        let entry = entry?;
+        if entry.file_type()?.is_dir() {
+            pids.append(&mut get_all_pids(&entry.path())?);
+        }
+    }
+    Ok(pids)
+}
+
+//删除root下的cgroups_path及其子cgroup, 父目录可能属于管理员或systemd, 这里不删除
+//cgroup目录中的文件不能删除, 只能从下往上rmdir
+pub fn remove_cgroup(root: &Path, cgroups_path: &Path) -> Result<()> {
+    let path = root.join(cgroups_path);
+    if path.exists() {
+        remove_cgroup_dir(&path)?;
+    }
+    Ok(())
+}
+
+fn remove_cgroup_dir(path: &Path) -> Result<()> {
+    for entry in fs::read_dir(path)? {
+        let entry = entry?;
+        if entry.file_type()?.is_dir() {
+            remove_cgroup_dir(&entry.path())?;
+        }
+    }
+    fs::remove_dir(path).with_context(|| format!("failed to remove cgroup {:?}", path))
+}
+
+//解析cpuset格式的列表, 如"0-3,5"
+pub fn parse_cpuset_list(list: &str) -> Result<BTreeSet<u32>> {
+    let mut set = BTreeSet::new();
+    for item in list.trim().split(',').filter(|s| !s.is_empty()) {
+        let (start, end) = match item.split_once('-') {
+            Some((start, end)) => (start.parse::<u32>()?, end.parse::<u32>()?),
+            None => {
+                let n = item.parse::<u32>()?;
+                (n, n)
+            }
+        };
+        if start > end {
+            bail!("invalid cpuset range {}", item);
+        }
+        set.extend(start..=end);
+    }
+    Ok(set)
+}
+
+//检查请求的cpu或内存节点是否都在available中
+pub fn check_cpuset(requested: &str, available: &str) -> Result<()> {
+    let available_set = parse_cpuset_list(available)?;
+    let missing: Vec<String> = parse_cpuset_list(requested)
+        .with_context(|| format!("invalid cpuset {:?}", requested))?
+        .into_iter()
+        .filter(|n| !available_set.contains(n))
+        .map(|n| n.to_string())
+        .collect();
+    if !missing.is_empty() {
+        bail!(
+            "{} do not exist, available: {}",
+            missing.join(","),
+            available.trim()
+        );
+    }
+    Ok(())
+}
+
+//块设备的"major:minor", 设置了path时从设备文件中获取
+pub fn device_number(device: &LinuxBlockIoDevice) -> Result<String> {
+    let path = match &device.path {
+        Some(path) => path,
+        None => return Ok(format!("{}:{}", device.major, device.minor)),
+    };
+    let st = stat(path).with_context(|| format!("failed to stat {:?}", path))?;
+    if SFlag::from_bits_truncate(st.st_mode) & SFlag::S_IFMT != SFlag::S_IFBLK {
+        bail!("{:?} is not a block device", path);
+    }
+    Ok(format!("{}:{}", major(st.st_rdev), minor(st.st_rdev)))
+}
+
+//容器中默认可以访问的设备, 追加在spec中的设备规则之后
+pub fn default_allowed_devices() -> Vec<LinuxDeviceCgroup> {
+    let char_device = |major: i64, minor: Option<i64>| LinuxDeviceCgroup {
+        allow: true,
+        typ: Some("c".to_owned()),
+        major: Some(major),
+        minor,
+        access: Some("rwm".to_owned()),
+    };
+    vec![
+        // /dev/null, /dev/zero, /dev/full, /dev/random, /dev/urandom
+        char_device(1, Some(3)),
+        char_device(1, Some(5)),
+        char_device(1, Some(7)),
+        char_device(1, Some(8)),
+        char_device(1, Some(9)),
+        // /dev/tty, /dev/ptmx
+        char_device(5, Some(0)),
+        char_device(5, Some(2)),
+        // /dev/pts/*, terminal为true时需要
+        char_device(136, None),
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    fn test_check_cpuset() {
+        let set = parse_cpuset_list("0-2,5\n").unwrap();
+        assert_eq!(set.into_iter().collect::<Vec<_>>(), vec![0, 1, 2, 5]);
+        assert!(parse_cpuset_list("3-1").is_err());
+
+        assert!(check_cpuset("1,5", "0-2,5").is_ok());
+        let err = check_cpuset("2-3", "0-1").unwrap_err();
+        assert_eq!(err.to_string(), "2,3 do not exist, available: 0-1");
+    }
+
+    #[test]
+    fn test_device_number() {
+        let mut device = LinuxBlockIoDevice {
+            major: 8,
+            minor: 16,
+            path: None,
+        };
+        assert_eq!(device_number(&device).unwrap(), "8:16");
+        device.path = Some("/dev/null".into());
+        assert!(device_number(&device).is_err());
+    }
+}
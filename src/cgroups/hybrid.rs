@@ -0,0 +1,61 @@
+use super::common::{self, ControllerOpt, CGROUP_PROCS};
+use super::stats::Stats;
+use super::v1;
+use super::{CgroupManager, FreezerState, OomNotifier};
+use crate::utils::fs;
+use anyhow::{Context, Result};
+use nix::unistd::Pid;
+use std::path::{Path, PathBuf};
+
+//hybrid模式: 资源限制由v1的controller完成, unified层级中没有controller,
+//只把进程放进去, 让systemd等依赖cgroup2的程序能够跟踪容器进程
+pub struct Manager {
+    v1: v1::manager::Manager,
+    unified_root: PathBuf,
+    cgroups_path: PathBuf,
+}
+
+impl Manager {
+    pub fn new(unified_root: PathBuf, cgroups_path: &Path) -> Manager {
+        Self {
+            v1: v1::manager::Manager::new(cgroups_path),
+            unified_root,
+            cgroups_path: cgroups_path.to_path_buf(),
+        }
+    }
+}
+
+impl CgroupManager for Manager {
+    fn add_task(&self, pid: Pid) -> Result<()> {
+        self.v1.add_task(pid)?;
+        let path = self.unified_root.join(&self.cgroups_path);
+        fs::create_dir_all(&path)?;
+        common::write_cgroup_file(path.join(CGROUP_PROCS), pid)
+            .with_context(|| format!("failed to add {} to cgroup {:?}", pid, path))
+    }
+
+    fn apply(&self, controller_opt: &ControllerOpt) -> Result<()> {
+        self.v1.apply(controller_opt)
+    }
+
+    fn get_all_pids(&self) -> Result<Vec<Pid>> {
+        self.v1.get_all_pids()
+    }
+
+    fn freeze(&self, state: FreezerState) -> Result<()> {
+        self.v1.freeze(state)
+    }
+
+    fn stats(&self) -> Result<Stats> {
+        self.v1.stats()
+    }
+
+    fn oom_notifier(&self) -> Result<Box<dyn OomNotifier>> {
+        self.v1.oom_notifier()
+    }
+
+    fn remove(&self) -> Result<()> {
+        self.v1.remove()?;
+        common::remove_cgroup(&self.unified_root, &self.cgroups_path)
+    }
+}
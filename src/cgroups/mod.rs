@@ -1,21 +1,330 @@
 pub mod common;
+pub mod hybrid;
+pub mod stats;
+pub mod systemd;
 pub mod v1;
 pub mod v2;
 
-use anyhow::Result;
+use anyhow::{bail, Context, Result};
 use common::ControllerOpt;
+use nix::sys::statfs::{statfs, CGROUP2_SUPER_MAGIC};
 use nix::unistd::Pid;
+use procfs::process::Process;
+use procfs::ProcessCgroup;
+use stats::Stats;
+use std::collections::HashMap;
+use std::fs;
+use std::os::unix::io::RawFd;
+use std::path::{Component, Path, PathBuf};
 
 pub const SMOG: &str = "smog";
 
 pub const DEFAULT_CGROUP_PATH: &str = "/sys/fs/cgroup";
 
+//容器cgroup相对于cgroup根目录的路径: 没有设置时为smog/<container_id>,
+//绝对路径相对于cgroup根目录, 相对路径相对于当前进程所在的cgroup
+pub fn cgroups_path(spec_path: Option<&Path>, container_id: &str) -> Result<PathBuf> {
+    let path = match spec_path {
+        Some(path) => path,
+        None => return Ok(PathBuf::from(SMOG).join(container_id)),
+    };
+    if path.components().any(|c| c == Component::ParentDir) {
+        bail!("cgroupsPath {:?} must not contain ..", path);
+    }
+    if let Ok(path) = path.strip_prefix("/") {
+        return Ok(path.to_path_buf());
+    }
+    Ok(own_cgroup()?.join(path))
+}
+
+//当前进程在cgroup2中的路径, 纯v1时各个controller的路径通常相同, 取第一个controller的
+fn own_cgroup() -> Result<PathBuf> {
+    let cgroups = Process::myself()?.cgroups()?;
+    let own = cgroups
+        .iter()
+        .find(|c| c.hierarchy == 0)
+        .or_else(|| cgroups.iter().find(|c| !c.controllers.is_empty()))
+        .ok_or_else(|| anyhow::anyhow!("failed to find the cgroup of current process"))?;
+    let pathname = Path::new(&own.pathname);
+    Ok(pathname.strip_prefix("/").unwrap_or(pathname).to_path_buf())
+}
+
+//创建cgroup之前, cgroups_path中在所有层级里都不存在的最上层目录, 即之后由smog创建的部分;
+//都已经存在时为cgroups_path本身
+pub fn created_prefix(cgroups_path: &Path) -> Result<PathBuf> {
+    Ok(created_prefix_in(&hierarchy_roots()?, cgroups_path))
+}
+
+fn created_prefix_in(roots: &[PathBuf], cgroups_path: &Path) -> PathBuf {
+    let mut prefix = PathBuf::new();
+    for component in cgroups_path.components() {
+        prefix.push(component);
+        if roots.iter().all(|root| !root.join(&prefix).exists()) {
+            return prefix;
+        }
+    }
+    cgroups_path.to_path_buf()
+}
+
+//容器的cgroup删除之后, 删除创建时产生的空父目录, 不超过created_prefix记录的目录
+pub fn remove_created_parents(cgroups_path: &Path, created: &Path) -> Result<()> {
+    for root in hierarchy_roots()? {
+        remove_parents_in(&root, cgroups_path, created);
+    }
+    Ok(())
+}
+
+fn remove_parents_in(root: &Path, cgroups_path: &Path, created: &Path) {
+    let mut parent = cgroups_path.parent();
+    while let Some(p) = parent {
+        // 父目录中还有其他容器的cgroup时rmdir会失败
+        if !p.starts_with(created) || fs::remove_dir(root.join(p)).is_err() {
+            break;
+        }
+        parent = p.parent();
+    }
+}
+
+//所有cgroup层级的挂载点, hybrid时包括v1的各个controller和unified
+fn hierarchy_roots() -> Result<Vec<PathBuf>> {
+    let v1_roots = || -> Result<Vec<PathBuf>> {
+        Ok(Process::myself()?
+            .mountinfo()?
+            .into_iter()
+            .filter(|m| m.fs_type == "cgroup" && m.mount_point.starts_with(DEFAULT_CGROUP_PATH))
+            .map(|m| m.mount_point)
+            .collect())
+    };
+    match get_cgroup_version()? {
+        CgroupVersion::V1 => v1_roots(),
+        CgroupVersion::V2(root) => Ok(vec![root]),
+        CgroupVersion::Hybrid(unified_root) => {
+            let mut roots = v1_roots()?;
+            roots.push(unified_root);
+            Ok(roots)
+        }
+    }
+}
+
+//当前进程在/sys/fs/cgroup下每个层级中所在的目录, 返回(挂载点, 宿主机上的目录),
+//用于只把容器自己的cgroup挂载到容器中
+pub fn own_hierarchy_dirs() -> Result<Vec<(PathBuf, PathBuf)>> {
+    let cgroups = Process::myself()?.cgroups()?;
+    let mut dirs = Vec::new();
+    for m in Process::myself()?.mountinfo()? {
+        if !m.mount_point.starts_with(DEFAULT_CGROUP_PATH)
+            || (m.fs_type != "cgroup" && m.fs_type != "cgroup2")
+        {
+            continue;
+        }
+        let own = find_own_cgroup(&cgroups, &m.fs_type, &m.super_options)
+            .ok_or_else(|| anyhow::anyhow!("failed to find the cgroup of {:?}", m.mount_point))?;
+        // 挂载的也可能是层级中的子目录
+        let relative = Path::new(&own.pathname)
+            .strip_prefix(&m.root)
+            .with_context(|| format!("cgroup {} is not under {:?}", own.pathname, m.mount_point))?;
+        dirs.push((m.mount_point.clone(), m.mount_point.join(relative)));
+    }
+    Ok(dirs)
+}
+
+//cgroup2对应hierarchy为0的项, v1根据挂载的super options匹配controller, 命名层级为name=xxx
+fn find_own_cgroup<'a>(
+    cgroups: &'a [ProcessCgroup],
+    fs_type: &str,
+    super_options: &HashMap<String, Option<String>>,
+) -> Option<&'a ProcessCgroup> {
+    if fs_type == "cgroup2" {
+        return cgroups.iter().find(|c| c.hierarchy == 0);
+    }
+    let mounted = |controller: &String| match controller.strip_prefix("name=") {
+        Some(name) => super_options.get("name") == Some(&Some(name.to_owned())),
+        None => super_options.contains_key(controller),
+    };
+    cgroups
+        .iter()
+        .find(|c| !c.controllers.is_empty() && c.controllers.iter().all(mounted))
+}
+
+#[derive(Debug, PartialEq)]
 pub enum CgroupVersion {
     V1,
-    V2,
+    //cgroup2的挂载点
+    V2(PathBuf),
+    //controller都在v1中, 同时在挂载点为unified的cgroup2中跟踪进程
+    Hybrid(PathBuf),
+}
+
+//默认路径挂载的是cgroup2时为v2, 否则根据挂载信息判断:
+//只有cgroup挂载为v1, 同时还有cgroup2挂载(一般在/sys/fs/cgroup/unified)为hybrid
+pub fn get_cgroup_version() -> Result<CgroupVersion> {
+    let default_root = Path::new(DEFAULT_CGROUP_PATH);
+    if default_root.exists() && statfs(default_root)?.filesystem_type() == CGROUP2_SUPER_MAGIC {
+        return Ok(CgroupVersion::V2(default_root.to_path_buf()));
+    }
+    let mounts: Vec<(String, PathBuf)> = Process::myself()?
+        .mountinfo()?
+        .into_iter()
+        .map(|m| (m.fs_type, m.mount_point))
+        .collect();
+    detect_version(&mounts)
+}
+
+fn detect_version(mounts: &[(String, PathBuf)]) -> Result<CgroupVersion> {
+    let has_v1 = mounts.iter().any(|(fs_type, _)| fs_type == "cgroup");
+    let mut unified: Vec<&PathBuf> = mounts
+        .iter()
+        .filter(|(fs_type, _)| fs_type == "cgroup2")
+        .map(|(_, mount_point)| mount_point)
+        .collect();
+    // 其他容器的cgroup2也可能出现在挂载信息中, 优先使用默认路径下的
+    unified.sort_by_key(|p| !p.starts_with(DEFAULT_CGROUP_PATH));
+    match (has_v1, unified.first()) {
+        (true, Some(unified)) => Ok(CgroupVersion::Hybrid(unified.to_path_buf())),
+        (true, None) => Ok(CgroupVersion::V1),
+        (false, Some(root)) => Ok(CgroupVersion::V2(root.to_path_buf())),
+        (false, None) => bail!("no cgroup filesystem is mounted"),
+    }
+}
+
+//cgroups_path是相对于cgroup根目录的路径, 根据宿主机的cgroup版本直接操作cgroup文件系统
+pub fn fs_manager(cgroups_path: &Path) -> Result<Box<dyn CgroupManager>> {
+    let m: Box<dyn CgroupManager> = match get_cgroup_version()? {
+        CgroupVersion::V1 => Box::new(v1::manager::Manager::new(cgroups_path)),
+        CgroupVersion::V2(root) => Box::new(v2::manager::Manager::new(root, cgroups_path)),
+        CgroupVersion::Hybrid(unified_root) => {
+            Box::new(hybrid::Manager::new(unified_root, cgroups_path))
+        }
+    };
+    Ok(m)
+}
+
+//freezer的目标状态
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FreezerState {
+    Frozen,
+    Thawed,
 }
 
 pub trait CgroupManager {
     fn add_task(&self, pid: Pid) -> Result<()>;
     fn apply(&self, controller_opt: &ControllerOpt) -> Result<()>;
+    fn get_all_pids(&self) -> Result<Vec<Pid>>;
+    //冻结或解冻cgroup中的所有进程, 返回时状态已经切换完成
+    fn freeze(&self, state: FreezerState) -> Result<()>;
+    fn stats(&self) -> Result<Stats>;
+    fn oom_notifier(&self) -> Result<Box<dyn OomNotifier>>;
+    fn remove(&self) -> Result<()>;
+}
+
+//OOM事件通知, fd可读时调用read获取新发生的OOM次数
+pub trait OomNotifier {
+    fn as_raw_fd(&self) -> RawFd;
+    fn read(&mut self) -> Result<u64>;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    fn test_detect_version() {
+        let mount = |fs_type: &str, path: &str| (fs_type.to_owned(), PathBuf::from(path));
+        let v1 = vec![
+            mount("tmpfs", "/sys/fs/cgroup"),
+            mount("cgroup", "/sys/fs/cgroup/cpu"),
+            mount("cgroup", "/sys/fs/cgroup/memory"),
+        ];
+        assert_eq!(detect_version(&v1).unwrap(), CgroupVersion::V1);
+
+        let mut hybrid = v1.clone();
+        hybrid.insert(0, mount("cgroup2", "/run/other/cgroup"));
+        hybrid.push(mount("cgroup2", "/sys/fs/cgroup/unified"));
+        assert_eq!(
+            detect_version(&hybrid).unwrap(),
+            CgroupVersion::Hybrid(PathBuf::from("/sys/fs/cgroup/unified"))
+        );
+
+        let v2 = vec![mount("cgroup2", "/mnt/cgroup")];
+        assert_eq!(
+            detect_version(&v2).unwrap(),
+            CgroupVersion::V2(PathBuf::from("/mnt/cgroup"))
+        );
+
+        assert!(detect_version(&[mount("tmpfs", "/sys/fs/cgroup")]).is_err());
+    }
+
+    #[test]
+    fn test_find_own_cgroup() {
+        let cgroup = |hierarchy, controllers: &[&str], pathname: &str| ProcessCgroup {
+            hierarchy,
+            controllers: controllers.iter().map(|c| c.to_string()).collect(),
+            pathname: pathname.to_owned(),
+        };
+        let cgroups = vec![
+            cgroup(3, &["name=systemd"], "/user.slice"),
+            cgroup(2, &["cpu", "cpuacct"], "/smog/abc"),
+            cgroup(1, &["memory"], "/smog/abc"),
+            cgroup(0, &[], "/"),
+        ];
+        let options = |options: &[(&str, Option<&str>)]| -> HashMap<String, Option<String>> {
+            options
+                .iter()
+                .map(|(k, v)| (k.to_string(), v.map(|v| v.to_owned())))
+                .collect()
+        };
+        let find = |fs_type, o| find_own_cgroup(&cgroups, fs_type, &o).map(|c| c.hierarchy);
+        assert_eq!(find("cgroup2", options(&[])), Some(0));
+        assert_eq!(
+            find(
+                "cgroup",
+                options(&[("rw", None), ("cpu", None), ("cpuacct", None)])
+            ),
+            Some(2)
+        );
+        assert_eq!(find("cgroup", options(&[("memory", None)])), Some(1));
+        assert_eq!(
+            find("cgroup", options(&[("name", Some("systemd"))])),
+            Some(3)
+        );
+        assert_eq!(find("cgroup", options(&[("pids", None)])), None);
+    }
+
+    #[test]
+    fn test_created_parents() {
+        let tmp = std::env::temp_dir().join(format!("smog-cgroup-{}", std::process::id()));
+        let roots = vec![tmp.join("cpu"), tmp.join("memory")];
+        fs::create_dir_all(roots[0].join("machine.slice")).unwrap();
+        fs::create_dir_all(&roots[1]).unwrap();
+        let cgroups_path = Path::new("machine.slice/smog/abc");
+        let created = created_prefix_in(&roots, cgroups_path);
+        assert_eq!(created, PathBuf::from("machine.slice/smog"));
+        assert_eq!(
+            created_prefix_in(&roots, Path::new("machine.slice")),
+            PathBuf::from("machine.slice")
+        );
+
+        for root in &roots {
+            fs::create_dir_all(root.join(cgroups_path)).unwrap();
+            fs::remove_dir(root.join(cgroups_path)).unwrap();
+            remove_parents_in(root, cgroups_path, &created);
+            assert!(!root.join("machine.slice/smog").exists());
+            assert!(root.join("machine.slice").exists());
+        }
+        fs::remove_dir_all(&tmp).unwrap();
+    }
+
+    #[test]
+    fn test_cgroups_path() {
+        assert_eq!(
+            cgroups_path(None, "abc").unwrap(),
+            PathBuf::from("smog/abc")
+        );
+        assert_eq!(
+            cgroups_path(Some(Path::new("/a/b")), "abc").unwrap(),
+            PathBuf::from("a/b")
+        );
+        assert!(cgroups_path(Some(Path::new("/a/../b")), "abc").is_err());
+        let relative = cgroups_path(Some(Path::new("a/b")), "abc").unwrap();
+        assert!(relative.is_relative() && relative.ends_with("a/b"));
+    }
 }
@@ -0,0 +1,132 @@
+use anyhow::{anyhow, Context, Result};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+//cgroup统计数据, 字段名与runc events的输出保持一致, 时间单位均为纳秒
+#[derive(Debug, Default, Clone, Serialize)]
+pub struct Stats {
+    pub cpu: CpuStats,
+    pub memory: MemoryStats,
+    pub pids: PidsStats,
+    pub blkio: BlkioStats,
+    pub hugetlb: HashMap<String, HugeTlbStats>,
+}
+
+#[derive(Debug, Default, Clone, Serialize)]
+pub struct CpuStats {
+    pub usage: CpuUsage,
+    pub throttling: CpuThrottling,
+}
+
+#[derive(Debug, Default, Clone, Serialize)]
+pub struct CpuUsage {
+    pub total: u64,
+    pub percpu: Vec<u64>,
+    pub kernel: u64,
+    pub user: u64,
+}
+
+#[derive(Debug, Default, Clone, Serialize)]
+pub struct CpuThrottling {
+    pub periods: u64,
+    #[serde(rename = "throttledPeriods")]
+    pub throttled_periods: u64,
+    #[serde(rename = "throttledTime")]
+    pub throttled_time: u64,
+}
+
+#[derive(Debug, Default, Clone, Serialize)]
+pub struct MemoryStats {
+    pub usage: MemoryEntry,
+    pub swap: MemoryEntry,
+    pub raw: HashMap<String, u64>,
+}
+
+#[derive(Debug, Default, Clone, Serialize)]
+pub struct MemoryEntry {
+    pub limit: u64,
+    pub usage: u64,
+    pub max: u64,
+    pub failcnt: u64,
+}
+
+#[derive(Debug, Default, Clone, Serialize)]
+pub struct PidsStats {
+    pub current: u64,
+    pub limit: u64,
+}
+
+#[derive(Debug, Default, Clone, Serialize)]
+pub struct BlkioStats {
+    #[serde(rename = "ioServiceBytesRecursive")]
+    pub io_service_bytes_recursive: Vec<BlkioEntry>,
+    #[serde(rename = "ioServicedRecursive")]
+    pub io_serviced_recursive: Vec<BlkioEntry>,
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Serialize)]
+pub struct BlkioEntry {
+    pub major: u64,
+    pub minor: u64,
+    pub op: String,
+    pub value: u64,
+}
+
+#[derive(Debug, Default, Clone, Serialize)]
+pub struct HugeTlbStats {
+    pub usage: u64,
+    pub max: u64,
+    pub failcnt: u64,
+}
+
+//读取只有一个值的cgroup文件, "max"表示没有限制
+pub fn parse_single_value(path: &Path) -> Result<u64> {
+    let content = fs::read_to_string(path).with_context(|| format!("failed to read {:?}", path))?;
+    let value = content.trim();
+    if value == "max" {
+        return Ok(u64::MAX);
+    }
+    value
+        .parse()
+        .with_context(|| format!("invalid value {:?} in {:?}", value, path))
+}
+
+//读取"key value"格式的cgroup文件, 例如cpu.stat和memory.stat
+pub fn parse_flat_keyed(path: &Path) -> Result<HashMap<String, u64>> {
+    let content = fs::read_to_string(path).with_context(|| format!("failed to read {:?}", path))?;
+    let mut values = HashMap::new();
+    for line in content.lines() {
+        let mut fields = line.split_whitespace();
+        if let (Some(key), Some(value)) = (fields.next(), fields.next()) {
+            let value = match value {
+                "max" => u64::MAX,
+                v => v
+                    .parse()
+                    .with_context(|| format!("invalid value {:?} in {:?}", v, path))?,
+            };
+            values.insert(key.to_owned(), value);
+        }
+    }
+    Ok(values)
+}
+
+//解析"major:minor"格式的设备号
+pub fn parse_device_number(device: &str) -> Result<(u64, u64)> {
+    let (major, minor) = device
+        .split_once(':')
+        .ok_or_else(|| anyhow!("invalid device number {:?}", device))?;
+    Ok((major.parse()?, minor.parse()?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    fn test_parse_device_number() {
+        assert_eq!(parse_device_number("8:16").unwrap(), (8, 16));
+        assert!(parse_device_number("8").is_err());
+        assert!(parse_device_number("a:b").is_err());
+    }
+}
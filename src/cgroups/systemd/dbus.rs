@@ -0,0 +1,689 @@
+use anyhow::{bail, Context, Result};
+use nix::unistd::getuid;
+use std::io::{Read, Write};
+use std::os::unix::net::UnixStream;
+use std::path::PathBuf;
+use std::time::Duration;
+
+// https://dbus.freedesktop.org/doc/dbus-specification.html
+const SYSTEM_BUS_ADDRESS: &str = "unix:path=/run/dbus/system_bus_socket";
+const DBUS: &str = "org.freedesktop.DBus";
+const DBUS_PATH: &str = "/org/freedesktop/DBus";
+const TIMEOUT: Duration = Duration::from_secs(30);
+
+const METHOD_CALL: u8 = 1;
+const METHOD_RETURN: u8 = 2;
+const ERROR: u8 = 3;
+const SIGNAL: u8 = 4;
+
+const FIELD_PATH: u8 = 1;
+const FIELD_INTERFACE: u8 = 2;
+const FIELD_MEMBER: u8 = 3;
+const FIELD_ERROR_NAME: u8 = 4;
+const FIELD_REPLY_SERIAL: u8 = 5;
+const FIELD_DESTINATION: u8 = 6;
+const FIELD_SIGNATURE: u8 = 8;
+
+//D-Bus中的值, 编码时签名由值推导, 解码时根据签名解析
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Byte(u8),
+    Bool(bool),
+    I16(i16),
+    U16(u16),
+    I32(i32),
+    U32(u32),
+    I64(i64),
+    U64(u64),
+    F64(f64),
+    //unix fd在消息中是fd数组的下标
+    UnixFd(u32),
+    Str(String),
+    ObjectPath(String),
+    Signature(String),
+    //元素的签名和所有元素, 空数组也需要元素签名
+    Array(String, Vec<Value>),
+    Struct(Vec<Value>),
+    Variant(Box<Value>),
+}
+
+impl Value {
+    pub fn signature(&self) -> String {
+        match self {
+            Value::Byte(_) => "y".to_owned(),
+            Value::Bool(_) => "b".to_owned(),
+            Value::I16(_) => "n".to_owned(),
+            Value::U16(_) => "q".to_owned(),
+            Value::I32(_) => "i".to_owned(),
+            Value::U32(_) => "u".to_owned(),
+            Value::I64(_) => "x".to_owned(),
+            Value::U64(_) => "t".to_owned(),
+            Value::F64(_) => "d".to_owned(),
+            Value::UnixFd(_) => "h".to_owned(),
+            Value::Str(_) => "s".to_owned(),
+            Value::ObjectPath(_) => "o".to_owned(),
+            Value::Signature(_) => "g".to_owned(),
+            Value::Array(sig, _) => format!("a{}", sig),
+            Value::Struct(fields) => {
+                let sigs: String = fields.iter().map(|f| f.signature()).collect();
+                format!("({})", sigs)
+            }
+            Value::Variant(_) => "v".to_owned(),
+        }
+    }
+
+    //字符串类的值, variant会被解开
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Value::Str(s) | Value::ObjectPath(s) | Value::Signature(s) => Some(s),
+            Value::Variant(v) => v.as_str(),
+            _ => None,
+        }
+    }
+}
+
+//D-Bus返回的错误, 调用方可以根据name判断错误类型
+#[derive(Debug)]
+pub struct DbusError {
+    pub name: String,
+    pub message: String,
+}
+
+impl std::fmt::Display for DbusError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: {}", self.name, self.message)
+    }
+}
+
+impl std::error::Error for DbusError {}
+
+//基本类型按自身大小对齐, 数组和字符串按长度字段对齐, struct和dict entry按8字节对齐
+fn alignment(sig: &str) -> usize {
+    match sig.as_bytes().first() {
+        Some(b'n' | b'q') => 2,
+        Some(b'b' | b'i' | b'u' | b'h' | b's' | b'o' | b'a') => 4,
+        Some(b'x' | b't' | b'd' | b'(' | b'{') => 8,
+        _ => 1,
+    }
+}
+
+//签名开头第一个完整类型的长度
+fn type_len(sig: &str) -> Result<usize> {
+    let bytes = sig.as_bytes();
+    match bytes.first() {
+        Some(b'a') => Ok(1 + type_len(&sig[1..])?),
+        Some(open @ (b'(' | b'{')) => {
+            let close = if *open == b'(' { b')' } else { b'}' };
+            let mut depth = 0;
+            for (i, b) in bytes.iter().enumerate() {
+                if b == open {
+                    depth += 1;
+                } else if *b == close {
+                    depth -= 1;
+                    if depth == 0 {
+                        return Ok(i + 1);
+                    }
+                }
+            }
+            bail!("unbalanced signature {:?}", sig)
+        }
+        Some(_) => Ok(1),
+        None => bail!("empty signature"),
+    }
+}
+
+//把签名拆分为多个完整类型
+fn split_signature(mut sig: &str) -> Result<Vec<&str>> {
+    let mut types = Vec::new();
+    while !sig.is_empty() {
+        let len = type_len(sig)?;
+        types.push(&sig[..len]);
+        sig = &sig[len..];
+    }
+    Ok(types)
+}
+
+//按小端编码, 对齐以消息开头为基准, 消息体从8字节对齐的位置开始, 所以可以单独编码
+#[derive(Default)]
+struct Encoder {
+    buf: Vec<u8>,
+}
+
+impl Encoder {
+    fn align(&mut self, n: usize) {
+        while !self.buf.len().is_multiple_of(n) {
+            self.buf.push(0);
+        }
+    }
+
+    //定长的基本类型按自身大小对齐
+    fn fixed<const N: usize>(&mut self, bytes: [u8; N]) {
+        self.align(N);
+        self.buf.extend_from_slice(&bytes);
+    }
+
+    fn u32(&mut self, v: u32) {
+        self.fixed(v.to_le_bytes());
+    }
+
+    fn value(&mut self, value: &Value) {
+        match value {
+            Value::Byte(b) => self.buf.push(*b),
+            Value::Bool(b) => self.u32(*b as u32),
+            Value::I16(n) => self.fixed(n.to_le_bytes()),
+            Value::U16(n) => self.fixed(n.to_le_bytes()),
+            Value::I32(n) => self.fixed(n.to_le_bytes()),
+            Value::U32(n) | Value::UnixFd(n) => self.u32(*n),
+            Value::I64(n) => self.fixed(n.to_le_bytes()),
+            Value::U64(n) => self.fixed(n.to_le_bytes()),
+            Value::F64(n) => self.fixed(n.to_le_bytes()),
+            Value::Str(s) | Value::ObjectPath(s) => {
+                self.u32(s.len() as u32);
+                self.buf.extend_from_slice(s.as_bytes());
+                self.buf.push(0);
+            }
+            Value::Signature(s) => {
+                self.buf.push(s.len() as u8);
+                self.buf.extend_from_slice(s.as_bytes());
+                self.buf.push(0);
+            }
+            Value::Array(sig, items) => {
+                self.u32(0);
+                let len_pos = self.buf.len() - 4;
+                // 长度之后到第一个元素之间的填充不计入数组长度
+                self.align(alignment(sig));
+                let start = self.buf.len();
+                for item in items {
+                    self.value(item);
+                }
+                let len = (self.buf.len() - start) as u32;
+                self.buf[len_pos..len_pos + 4].copy_from_slice(&len.to_le_bytes());
+            }
+            Value::Struct(fields) => {
+                self.align(8);
+                for field in fields {
+                    self.value(field);
+                }
+            }
+            Value::Variant(v) => {
+                self.value(&Value::Signature(v.signature()));
+                self.value(v);
+            }
+        }
+    }
+}
+
+struct Decoder<'a> {
+    buf: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Decoder<'a> {
+    fn align(&mut self, n: usize) {
+        self.pos = self.pos.div_ceil(n) * n;
+    }
+
+    fn take(&mut self, n: usize) -> Result<&'a [u8]> {
+        if self.pos + n > self.buf.len() {
+            bail!("dbus message is truncated");
+        }
+        let bytes = &self.buf[self.pos..self.pos + n];
+        self.pos += n;
+        Ok(bytes)
+    }
+
+    fn fixed<const N: usize>(&mut self) -> Result<[u8; N]> {
+        self.align(N);
+        let mut bytes = [0u8; N];
+        bytes.copy_from_slice(self.take(N)?);
+        Ok(bytes)
+    }
+
+    fn u32(&mut self) -> Result<u32> {
+        Ok(u32::from_le_bytes(self.fixed()?))
+    }
+
+    fn string(&mut self, len: usize) -> Result<String> {
+        let s = String::from_utf8(self.take(len)?.to_vec())?;
+        // 结尾的\0
+        self.take(1)?;
+        Ok(s)
+    }
+
+    //sig必须是一个完整类型
+    fn value(&mut self, sig: &str) -> Result<Value> {
+        let value = match sig.as_bytes().first() {
+            Some(b'y') => Value::Byte(self.take(1)?[0]),
+            Some(b'b') => Value::Bool(self.u32()? != 0),
+            Some(b'n') => Value::I16(i16::from_le_bytes(self.fixed()?)),
+            Some(b'q') => Value::U16(u16::from_le_bytes(self.fixed()?)),
+            Some(b'i') => Value::I32(i32::from_le_bytes(self.fixed()?)),
+            Some(b'u') => Value::U32(self.u32()?),
+            Some(b'h') => Value::UnixFd(self.u32()?),
+            Some(b'x') => Value::I64(i64::from_le_bytes(self.fixed()?)),
+            Some(b't') => Value::U64(u64::from_le_bytes(self.fixed()?)),
+            Some(b'd') => Value::F64(f64::from_le_bytes(self.fixed()?)),
+            Some(b's') => {
+                let len = self.u32()? as usize;
+                Value::Str(self.string(len)?)
+            }
+            Some(b'o') => {
+                let len = self.u32()? as usize;
+                Value::ObjectPath(self.string(len)?)
+            }
+            Some(b'g') => {
+                let len = self.take(1)?[0] as usize;
+                Value::Signature(self.string(len)?)
+            }
+            Some(b'a') => {
+                let len = self.u32()? as usize;
+                let elem = &sig[1..];
+                self.align(alignment(elem));
+                let end = self.pos + len;
+                let mut items = Vec::new();
+                while self.pos < end {
+                    items.push(self.value(elem)?);
+                }
+                Value::Array(elem.to_owned(), items)
+            }
+            // dict entry按struct处理
+            Some(b'(' | b'{') => {
+                self.align(8);
+                let mut fields = Vec::new();
+                for field in split_signature(&sig[1..sig.len() - 1])? {
+                    fields.push(self.value(field)?);
+                }
+                Value::Struct(fields)
+            }
+            Some(b'v') => {
+                let sig = self.value("g")?;
+                let sig = sig.as_str().unwrap_or_default().to_owned();
+                Value::Variant(Box::new(self.value(&sig)?))
+            }
+            _ => bail!("unsupported dbus signature {:?}", sig),
+        };
+        Ok(value)
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct Message {
+    pub typ: u8,
+    pub serial: u32,
+    pub reply_serial: Option<u32>,
+    pub path: Option<String>,
+    pub interface: Option<String>,
+    pub member: Option<String>,
+    pub error_name: Option<String>,
+    pub destination: Option<String>,
+    pub signature: String,
+    pub body: Vec<Value>,
+}
+
+impl Message {
+    pub fn method_call(destination: &str, path: &str, interface: &str, member: &str) -> Self {
+        Self {
+            typ: METHOD_CALL,
+            path: Some(path.to_owned()),
+            interface: Some(interface.to_owned()),
+            member: Some(member.to_owned()),
+            destination: Some(destination.to_owned()),
+            ..Default::default()
+        }
+    }
+
+    //以下用于在测试中模拟总线的另一端
+    #[cfg(test)]
+    pub fn method_return(reply_serial: u32, body: Vec<Value>) -> Self {
+        Self {
+            typ: METHOD_RETURN,
+            reply_serial: Some(reply_serial),
+            body,
+            ..Default::default()
+        }
+    }
+
+    #[cfg(test)]
+    pub fn error(reply_serial: u32, name: &str, message: &str) -> Self {
+        Self {
+            typ: ERROR,
+            reply_serial: Some(reply_serial),
+            error_name: Some(name.to_owned()),
+            body: vec![Value::Str(message.to_owned())],
+            ..Default::default()
+        }
+    }
+
+    #[cfg(test)]
+    pub fn signal(path: &str, interface: &str, member: &str, body: Vec<Value>) -> Self {
+        Self {
+            typ: SIGNAL,
+            path: Some(path.to_owned()),
+            interface: Some(interface.to_owned()),
+            member: Some(member.to_owned()),
+            body,
+            ..Default::default()
+        }
+    }
+
+    pub fn is_signal(&self, member: &str) -> bool {
+        self.typ == SIGNAL && self.member.as_deref() == Some(member)
+    }
+
+    pub fn encode(&self) -> Vec<u8> {
+        let mut body = Encoder::default();
+        for value in &self.body {
+            body.value(value);
+        }
+        let signature: String = self.body.iter().map(|v| v.signature()).collect();
+
+        let mut fields = Vec::new();
+        let mut field = |code: u8, value: Value| {
+            fields.push(Value::Struct(vec![
+                Value::Byte(code),
+                Value::Variant(Box::new(value)),
+            ]));
+        };
+        if let Some(path) = &self.path {
+            field(FIELD_PATH, Value::ObjectPath(path.clone()));
+        }
+        if let Some(interface) = &self.interface {
+            field(FIELD_INTERFACE, Value::Str(interface.clone()));
+        }
+        if let Some(member) = &self.member {
+            field(FIELD_MEMBER, Value::Str(member.clone()));
+        }
+        if let Some(name) = &self.error_name {
+            field(FIELD_ERROR_NAME, Value::Str(name.clone()));
+        }
+        if let Some(serial) = self.reply_serial {
+            field(FIELD_REPLY_SERIAL, Value::U32(serial));
+        }
+        if let Some(destination) = &self.destination {
+            field(FIELD_DESTINATION, Value::Str(destination.clone()));
+        }
+        if !signature.is_empty() {
+            field(FIELD_SIGNATURE, Value::Signature(signature));
+        }
+
+        let mut enc = Encoder::default();
+        enc.buf.extend_from_slice(&[b'l', self.typ, 0, 1]);
+        enc.u32(body.buf.len() as u32);
+        enc.u32(self.serial);
+        enc.value(&Value::Array("(yv)".to_owned(), fields));
+        enc.align(8);
+        enc.buf.extend_from_slice(&body.buf);
+        enc.buf
+    }
+
+    pub fn read<R: Read>(r: &mut R) -> Result<Message> {
+        let mut buf = vec![0u8; 16];
+        r.read_exact(&mut buf)?;
+        if buf[0] != b'l' {
+            bail!("only little endian dbus messages are supported");
+        }
+        let u32_at = |buf: &[u8], pos: usize| {
+            let mut bytes = [0u8; 4];
+            bytes.copy_from_slice(&buf[pos..pos + 4]);
+            u32::from_le_bytes(bytes) as usize
+        };
+        let body_len = u32_at(&buf, 4);
+        let header_len = (16 + u32_at(&buf, 12)).div_ceil(8) * 8;
+        buf.resize(header_len + body_len, 0);
+        r.read_exact(&mut buf[16..])?;
+
+        let mut msg = Message {
+            typ: buf[1],
+            serial: u32_at(&buf, 8) as u32,
+            ..Default::default()
+        };
+        let mut header = Decoder {
+            buf: &buf[..header_len],
+            pos: 12,
+        };
+        if let Value::Array(_, fields) = header.value("a(yv)")? {
+            for field in fields {
+                let (code, value) = match field {
+                    Value::Struct(f) if f.len() == 2 => (f[0].clone(), f[1].clone()),
+                    _ => continue,
+                };
+                let s = value.as_str().map(|s| s.to_owned());
+                match (code, value) {
+                    (Value::Byte(FIELD_PATH), _) => msg.path = s,
+                    (Value::Byte(FIELD_INTERFACE), _) => msg.interface = s,
+                    (Value::Byte(FIELD_MEMBER), _) => msg.member = s,
+                    (Value::Byte(FIELD_ERROR_NAME), _) => msg.error_name = s,
+                    (Value::Byte(FIELD_DESTINATION), _) => msg.destination = s,
+                    (Value::Byte(FIELD_SIGNATURE), _) => msg.signature = s.unwrap_or_default(),
+                    (Value::Byte(FIELD_REPLY_SERIAL), Value::Variant(v)) => {
+                        if let Value::U32(serial) = *v {
+                            msg.reply_serial = Some(serial);
+                        }
+                    }
+                    _ => {}
+                }
+            }
+        }
+        let mut body = Decoder {
+            buf: &buf[header_len..],
+            pos: 0,
+        };
+        for sig in split_signature(&msg.signature)? {
+            msg.body.push(body.value(sig)?);
+        }
+        Ok(msg)
+    }
+}
+
+//系统总线的地址, 可以通过DBUS_SYSTEM_BUS_ADDRESS覆盖
+pub fn system_bus_address() -> String {
+    std::env::var("DBUS_SYSTEM_BUS_ADDRESS").unwrap_or_else(|_| SYSTEM_BUS_ADDRESS.to_owned())
+}
+
+//rootless模式下使用用户总线, 没有设置DBUS_SESSION_BUS_ADDRESS时在$XDG_RUNTIME_DIR/bus
+pub fn session_bus_address() -> Result<String> {
+    if let Ok(address) = std::env::var("DBUS_SESSION_BUS_ADDRESS") {
+        return Ok(address);
+    }
+    let runtime_dir = std::env::var("XDG_RUNTIME_DIR")
+        .context("neither DBUS_SESSION_BUS_ADDRESS nor XDG_RUNTIME_DIR is set")?;
+    Ok(format!("unix:path={}/bus", runtime_dir))
+}
+
+//地址格式为transport:key=value,...; 多个地址用;分隔, 这里只支持unix:path=
+fn socket_path(address: &str) -> Result<PathBuf> {
+    address
+        .split(';')
+        .filter_map(|a| a.strip_prefix("unix:"))
+        .flat_map(|opts| opts.split(','))
+        .find_map(|opt| opt.strip_prefix("path="))
+        .map(PathBuf::from)
+        .with_context(|| format!("unsupported dbus address {:?}", address))
+}
+
+pub struct Connection {
+    stream: UnixStream,
+    serial: u32,
+    //等待方法返回时收到的signal, 留给wait_signal处理
+    signals: Vec<Message>,
+}
+
+impl Connection {
+    pub fn connect(address: &str) -> Result<Connection> {
+        let path = socket_path(address)?;
+        let stream = UnixStream::connect(&path)
+            .with_context(|| format!("failed to connect to dbus {:?}", path))?;
+        stream.set_read_timeout(Some(TIMEOUT))?;
+        let mut conn = Self {
+            stream,
+            serial: 0,
+            signals: Vec::new(),
+        };
+        conn.auth()?;
+        conn.call(Message::method_call(DBUS, DBUS_PATH, DBUS, "Hello"))?;
+        Ok(conn)
+    }
+
+    //使用EXTERNAL机制认证, 身份是十六进制编码的uid字符串
+    fn auth(&mut self) -> Result<()> {
+        let uid: String = getuid()
+            .to_string()
+            .bytes()
+            .map(|b| format!("{:02x}", b))
+            .collect();
+        self.stream
+            .write_all(format!("\0AUTH EXTERNAL {}\r\n", uid).as_bytes())?;
+        let line = read_line(&mut self.stream)?;
+        if !line.starts_with("OK ") {
+            bail!("dbus authentication failed: {}", line);
+        }
+        self.stream.write_all(b"BEGIN\r\n")?;
+        Ok(())
+    }
+
+    //订阅signal, 只有匹配规则的signal才会被总线转发过来
+    pub fn add_match(&mut self, rule: &str) -> Result<()> {
+        let mut msg = Message::method_call(DBUS, DBUS_PATH, DBUS, "AddMatch");
+        msg.body = vec![Value::Str(rule.to_owned())];
+        self.call(msg)?;
+        Ok(())
+    }
+
+    //发送方法调用并等待返回, 错误转换为DbusError
+    pub fn call(&mut self, mut msg: Message) -> Result<Vec<Value>> {
+        self.serial += 1;
+        msg.serial = self.serial;
+        self.stream.write_all(&msg.encode())?;
+        loop {
+            let reply = Message::read(&mut self.stream)?;
+            if reply.typ == SIGNAL {
+                self.signals.push(reply);
+                continue;
+            }
+            if reply.reply_serial != Some(msg.serial) {
+                continue;
+            }
+            match reply.typ {
+                METHOD_RETURN => return Ok(reply.body),
+                ERROR => {
+                    return Err(DbusError {
+                        name: reply.error_name.unwrap_or_default(),
+                        message: reply
+                            .body
+                            .first()
+                            .and_then(|v| v.as_str())
+                            .unwrap_or_default()
+                            .to_owned(),
+                    }
+                    .into())
+                }
+                _ => {}
+            }
+        }
+    }
+
+    //等待满足条件的signal, 返回它的消息体
+    pub fn wait_signal<F>(&mut self, member: &str, matches: F) -> Result<Vec<Value>>
+    where
+        F: Fn(&[Value]) -> bool,
+    {
+        if let Some(i) = self
+            .signals
+            .iter()
+            .position(|s| s.is_signal(member) && matches(&s.body))
+        {
+            return Ok(self.signals.remove(i).body);
+        }
+        loop {
+            let msg = Message::read(&mut self.stream)
+                .with_context(|| format!("failed to wait for signal {}", member))?;
+            if msg.is_signal(member) && matches(&msg.body) {
+                return Ok(msg.body);
+            }
+        }
+    }
+}
+
+//认证阶段是以\r\n结尾的文本行
+pub fn read_line<R: Read>(r: &mut R) -> Result<String> {
+    let mut line = Vec::new();
+    let mut b = [0u8; 1];
+    while !line.ends_with(b"\r\n") {
+        r.read_exact(&mut b)?;
+        line.push(b[0]);
+    }
+    line.truncate(line.len() - 2);
+    Ok(String::from_utf8(line)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    fn test_message_roundtrip() {
+        let mut msg = Message::method_call("a.b", "/a/b", "a.b.C", "Call");
+        msg.serial = 7;
+        msg.body = vec![
+            Value::Str("unit.scope".to_owned()),
+            Value::Array(
+                "(sv)".to_owned(),
+                vec![
+                    Value::Struct(vec![
+                        Value::Str("PIDs".to_owned()),
+                        Value::Variant(Box::new(Value::Array("u".to_owned(), vec![Value::U32(1)]))),
+                    ]),
+                    Value::Struct(vec![
+                        Value::Str("MemoryMax".to_owned()),
+                        Value::Variant(Box::new(Value::U64(u64::MAX))),
+                    ]),
+                ],
+            ),
+            Value::Array("(sa(sv))".to_owned(), vec![]),
+        ];
+        let decoded = Message::read(&mut msg.encode().as_slice()).unwrap();
+        assert_eq!(decoded.signature, "sa(sv)a(sa(sv))");
+        assert_eq!(decoded.serial, 7);
+        assert_eq!(decoded.member.as_deref(), Some("Call"));
+        assert_eq!(decoded.path.as_deref(), Some("/a/b"));
+        assert_eq!(decoded.body, msg.body);
+    }
+
+    #[test]
+    fn test_basic_types() {
+        // 总线上其他服务的信号可能包含任意基本类型, 每个值前面的byte用于检查对齐
+        let body = vec![
+            Value::Byte(1),
+            Value::I16(-2),
+            Value::Byte(3),
+            Value::U16(4),
+            Value::Byte(5),
+            Value::I32(-6),
+            Value::Byte(7),
+            Value::I64(-8),
+            Value::Byte(9),
+            Value::F64(1.5),
+            Value::Byte(10),
+            Value::UnixFd(0),
+            Value::Variant(Box::new(Value::Array("n".to_owned(), vec![Value::I16(11)]))),
+        ];
+        let mut msg = Message::signal("/a/b", "a.b.C", "Changed", body);
+        msg.serial = 3;
+        let decoded = Message::read(&mut msg.encode().as_slice()).unwrap();
+        assert_eq!(decoded.signature, "ynyqyiyxydyhv");
+        assert!(decoded.is_signal("Changed"));
+        assert_eq!(decoded.body, msg.body);
+    }
+
+    #[test]
+    fn test_socket_path() {
+        assert_eq!(
+            socket_path("unix:path=/run/dbus/system_bus_socket").unwrap(),
+            PathBuf::from("/run/dbus/system_bus_socket")
+        );
+        assert_eq!(
+            socket_path("tcp:host=a;unix:guid=1,path=/tmp/bus").unwrap(),
+            PathBuf::from("/tmp/bus")
+        );
+        assert!(socket_path("unix:abstract=/tmp/bus").is_err());
+    }
+}
@@ -0,0 +1,723 @@
+mod dbus;
+
+use super::common::{self, ControllerOpt};
+use super::stats::Stats;
+use super::v2::cpu::Cpu;
+use super::v2::io::Io;
+use super::v2::memory::Memory;
+use super::{get_cgroup_version, CgroupManager, CgroupVersion, FreezerState, OomNotifier, SMOG};
+use crate::oci::oci::{
+    LinuxBlockIo, LinuxBlockIoDevice, LinuxCpu, LinuxMemory, LinuxResources, LinuxWeightDevice,
+};
+use anyhow::{bail, Context, Result};
+use dbus::{Connection, DbusError, Message, Value};
+use nix::unistd::Pid;
+use std::path::{Path, PathBuf};
+
+const SYSTEMD: &str = "org.freedesktop.systemd1";
+const SYSTEMD_PATH: &str = "/org/freedesktop/systemd1";
+const MANAGER_INTERFACE: &str = "org.freedesktop.systemd1.Manager";
+const SCOPE_INTERFACE: &str = "org.freedesktop.systemd1.Scope";
+const PROPERTIES_INTERFACE: &str = "org.freedesktop.DBus.Properties";
+const NO_SUCH_UNIT: &str = "org.freedesktop.systemd1.NoSuchUnit";
+const DEFAULT_SLICE: &str = "system.slice";
+const DEFAULT_USER_SLICE: &str = "user.slice";
+const DEFAULT_CPU_PERIOD: u64 = 100000;
+
+//systemd驱动的cgroupsPath格式为slice:prefix:name, 没有设置时为<默认slice>:smog:<container_id>
+pub fn unit_path(spec_path: Option<&Path>, container_id: &str, rootless: bool) -> Result<PathBuf> {
+    let default_slice = if rootless {
+        DEFAULT_USER_SLICE
+    } else {
+        DEFAULT_SLICE
+    };
+    let path = match spec_path {
+        Some(path) => path,
+        None => {
+            return Ok(PathBuf::from(format!(
+                "{}:{}:{}",
+                default_slice, SMOG, container_id
+            )))
+        }
+    };
+    let (slice, prefix, name) = split_path(path)?;
+    let slice = if slice.is_empty() {
+        default_slice
+    } else {
+        slice
+    };
+    Ok(PathBuf::from(format!("{}:{}:{}", slice, prefix, name)))
+}
+
+fn split_path(path: &Path) -> Result<(&str, &str, &str)> {
+    let parts: Vec<&str> = path.to_str().unwrap_or_default().split(':').collect();
+    match parts[..] {
+        [slice, prefix, name] if !name.is_empty() => {
+            if !slice.is_empty() && !slice.ends_with(".slice") {
+                bail!("invalid slice {:?} in cgroupsPath", slice);
+            }
+            Ok((slice, prefix, name))
+        }
+        _ => bail!(
+            "cgroupsPath {:?} should be slice:prefix:name for the systemd cgroup driver",
+            path
+        ),
+    }
+}
+
+//容器进程放在systemd创建的transient scope中, systemd支持的资源限制通过unit属性设置,
+//其余的操作交给cgroup文件系统上对应的manager完成
+pub struct Manager {
+    slice: String,
+    unit: String,
+    address: String,
+}
+
+impl Manager {
+    pub fn new(cgroups_path: &Path, rootless: bool) -> Result<Manager> {
+        let address = if rootless {
+            dbus::session_bus_address()?
+        } else {
+            dbus::system_bus_address()
+        };
+        Self::with_address(cgroups_path, address)
+    }
+
+    fn with_address(cgroups_path: &Path, address: String) -> Result<Manager> {
+        let (slice, prefix, name) = split_path(cgroups_path)?;
+        let unit = if prefix.is_empty() {
+            format!("{}.scope", name)
+        } else {
+            format!("{}-{}.scope", prefix, name)
+        };
+        Ok(Self {
+            slice: slice.to_owned(),
+            unit,
+            address,
+        })
+    }
+
+    fn connect(&self) -> Result<Connection> {
+        Connection::connect(&self.address)
+    }
+
+    fn call(
+        &self,
+        conn: &mut Connection,
+        path: &str,
+        interface: &str,
+        member: &str,
+        body: Vec<Value>,
+    ) -> Result<Vec<Value>> {
+        let mut msg = Message::method_call(SYSTEMD, path, interface, member);
+        msg.body = body;
+        conn.call(msg)
+            .with_context(|| format!("failed to call {} for unit {}", member, self.unit))
+    }
+
+    //unit所在的cgroup, 相对于cgroup根目录; unit不存在时返回None
+    fn control_group(&self, conn: &mut Connection) -> Result<Option<PathBuf>> {
+        let unit_path = match self.call(
+            conn,
+            SYSTEMD_PATH,
+            MANAGER_INTERFACE,
+            "GetUnit",
+            vec![Value::Str(self.unit.clone())],
+        ) {
+            Ok(reply) => reply_str(&reply)?,
+            Err(err) if is_no_such_unit(&err) => return Ok(None),
+            Err(err) => return Err(err),
+        };
+        let reply = self.call(
+            conn,
+            &unit_path,
+            PROPERTIES_INTERFACE,
+            "Get",
+            vec![
+                Value::Str(SCOPE_INTERFACE.to_owned()),
+                Value::Str("ControlGroup".to_owned()),
+            ],
+        )?;
+        let path = reply_str(&reply)?;
+        Ok(Some(PathBuf::from(path.trim_start_matches('/'))))
+    }
+
+    //cgroup文件系统上操作unit所在cgroup的manager
+    fn fs_manager(&self) -> Result<Box<dyn CgroupManager>> {
+        let mut conn = self.connect()?;
+        match self.control_group(&mut conn)? {
+            Some(path) => super::fs_manager(&path),
+            None => bail!("unit {} does not exist", self.unit),
+        }
+    }
+
+    //创建包含pid的transient scope, 等待启动的job完成
+    fn start_unit(&self, conn: &mut Connection, pid: Pid) -> Result<()> {
+        conn.add_match(&format!(
+            "type='signal',interface='{}',member='JobRemoved'",
+            MANAGER_INTERFACE
+        ))?;
+        // systemd只在有客户端订阅时才发送job相关的signal
+        self.call(conn, SYSTEMD_PATH, MANAGER_INTERFACE, "Subscribe", vec![])?;
+        let properties = vec![
+            property(
+                "Description",
+                Value::Str(format!("smog container {}", self.unit)),
+            ),
+            property("Slice", Value::Str(self.slice.clone())),
+            property(
+                "PIDs",
+                Value::Array("u".to_owned(), vec![Value::U32(pid.as_raw() as u32)]),
+            ),
+            // 允许在scope中创建子cgroup和写入controller文件
+            property("Delegate", Value::Bool(true)),
+            property("DefaultDependencies", Value::Bool(false)),
+            property("CPUAccounting", Value::Bool(true)),
+            property("MemoryAccounting", Value::Bool(true)),
+            property("TasksAccounting", Value::Bool(true)),
+        ];
+        let reply = self.call(
+            conn,
+            SYSTEMD_PATH,
+            MANAGER_INTERFACE,
+            "StartTransientUnit",
+            vec![
+                Value::Str(self.unit.clone()),
+                Value::Str("replace".to_owned()),
+                Value::Array("(sv)".to_owned(), properties),
+                Value::Array("(sa(sv))".to_owned(), vec![]),
+            ],
+        )?;
+        let job = reply_str(&reply)?;
+        // JobRemoved(id, job, unit, result)
+        let body = conn.wait_signal("JobRemoved", |body| {
+            body.get(1).and_then(|v| v.as_str()) == Some(job.as_str())
+        })?;
+        match body.get(3).and_then(|v| v.as_str()) {
+            Some("done") => Ok(()),
+            result => bail!("failed to start unit {}: {:?}", self.unit, result),
+        }
+    }
+
+    //只修改运行时属性, 不写入unit的配置文件
+    fn set_properties(
+        &self,
+        conn: &mut Connection,
+        resources: &LinuxResources,
+        unified: bool,
+    ) -> Result<()> {
+        let properties = properties(resources, unified)?;
+        if properties.is_empty() {
+            return Ok(());
+        }
+        self.call(
+            conn,
+            SYSTEMD_PATH,
+            MANAGER_INTERFACE,
+            "SetUnitProperties",
+            vec![
+                Value::Str(self.unit.clone()),
+                Value::Bool(true),
+                Value::Array("(sv)".to_owned(), properties),
+            ],
+        )?;
+        Ok(())
+    }
+}
+
+impl CgroupManager for Manager {
+    fn add_task(&self, pid: Pid) -> Result<()> {
+        let mut conn = self.connect()?;
+        match self.control_group(&mut conn)? {
+            // exec时unit已经存在, 直接把进程加入unit的cgroup
+            Some(path) => super::fs_manager(&path)?.add_task(pid),
+            None => self.start_unit(&mut conn, pid),
+        }
+    }
+
+    //systemd在daemon-reload等时候会重新应用unit属性, 有对应属性的资源只通过属性设置,
+    //否则直接写入的值会被覆盖
+    fn apply(&self, controller_opt: &ControllerOpt) -> Result<()> {
+        let unified = matches!(get_cgroup_version()?, CgroupVersion::V2(_));
+        let mut conn = self.connect()?;
+        self.set_properties(&mut conn, controller_opt.resources, unified)?;
+        let raw = raw_resources(controller_opt.resources, unified);
+        self.fs_manager()?.apply(&ControllerOpt { resources: &raw })
+    }
+
+    fn get_all_pids(&self) -> Result<Vec<Pid>> {
+        self.fs_manager()?.get_all_pids()
+    }
+
+    fn freeze(&self, state: FreezerState) -> Result<()> {
+        self.fs_manager()?.freeze(state)
+    }
+
+    fn stats(&self) -> Result<Stats> {
+        self.fs_manager()?.stats()
+    }
+
+    fn oom_notifier(&self) -> Result<Box<dyn OomNotifier>> {
+        self.fs_manager()?.oom_notifier()
+    }
+
+    //scope中的进程全部退出后systemd会自动清理, 这里的unit可能已经不存在
+    fn remove(&self) -> Result<()> {
+        let mut conn = self.connect()?;
+        match self.call(
+            &mut conn,
+            SYSTEMD_PATH,
+            MANAGER_INTERFACE,
+            "StopUnit",
+            vec![
+                Value::Str(self.unit.clone()),
+                Value::Str("replace".to_owned()),
+            ],
+        ) {
+            Err(err) if !is_no_such_unit(&err) => Err(err),
+            _ => Ok(()),
+        }
+    }
+}
+
+fn property(name: &str, value: Value) -> Value {
+    Value::Struct(vec![
+        Value::Str(name.to_owned()),
+        Value::Variant(Box::new(value)),
+    ])
+}
+
+fn reply_str(reply: &[Value]) -> Result<String> {
+    reply
+        .first()
+        .and_then(|v| v.as_str())
+        .map(|s| s.to_owned())
+        .context("unexpected dbus reply")
+}
+
+fn is_no_such_unit(err: &anyhow::Error) -> bool {
+    err.downcast_ref::<DbusError>()
+        .map(|e| e.name == NO_SUCH_UNIT)
+        .unwrap_or(false)
+}
+
+//把LinuxResources中systemd有对应属性的部分转换为unit属性, 不限制时使用u64::MAX(infinity);
+//unified为false时controller在v1中, 使用legacy层级的属性
+fn properties(resources: &LinuxResources, unified: bool) -> Result<Vec<Value>> {
+    let mut properties = Vec::new();
+    if let Some(cpu) = &resources.cpu {
+        if let Some(shares) = cpu.shares.filter(|s| *s != 0) {
+            properties.push(match unified {
+                true => property(
+                    "CPUWeight",
+                    Value::U64(Cpu::convert_shares_to_cgroup2(shares)),
+                ),
+                false => property("CPUShares", Value::U64(shares)),
+            });
+        }
+        if let Some(period) = cpu.period.filter(|p| *p != 0) {
+            properties.push(property("CPUQuotaPeriodUSec", Value::U64(period)));
+        }
+        if let Some(quota) = cpu.quota {
+            let quota_per_sec = if quota > 0 {
+                let period = cpu.period.filter(|p| *p != 0).unwrap_or(DEFAULT_CPU_PERIOD);
+                // systemd内部把配额转换为百分比, 向上取整到10ms, 否则会比要求的更小
+                let usec = quota as u64 * 1_000_000 / period;
+                usec.div_ceil(10000) * 10000
+            } else {
+                u64::MAX
+            };
+            properties.push(property("CPUQuotaPerSecUSec", Value::U64(quota_per_sec)));
+        }
+    }
+    if let Some(memory) = &resources.memory {
+        let value = |v: i64| Value::U64(if v >= 0 { v as u64 } else { u64::MAX });
+        if let Some(limit) = memory.limit {
+            let name = if unified { "MemoryMax" } else { "MemoryLimit" };
+            properties.push(property(name, value(limit)));
+        }
+        if unified {
+            if let Some(reservation) = memory.reservation {
+                properties.push(property("MemoryLow", value(reservation)));
+            }
+            if let Some(swap) = Memory::swap_max(memory)? {
+                properties.push(property("MemorySwapMax", value(swap)));
+            }
+        }
+    }
+    if let Some(pids) = &resources.pids {
+        let limit = if pids.limit > 0 {
+            pids.limit as u64
+        } else {
+            u64::MAX
+        };
+        properties.push(property("TasksMax", Value::U64(limit)));
+    }
+    if let Some(block_io) = &resources.block_io {
+        block_io_properties(block_io, unified, &mut properties)?;
+    }
+    Ok(properties)
+}
+
+//unified中使用IO*属性, legacy中使用BlockIO*属性, 后者没有iops限制
+fn block_io_properties(
+    block_io: &LinuxBlockIo,
+    unified: bool,
+    properties: &mut Vec<Value>,
+) -> Result<()> {
+    let weight = |weight: u16| -> Result<u64> {
+        if !(10..=1000).contains(&weight) {
+            bail!("invalid blkio weight {}, must be in 10-1000", weight);
+        }
+        Ok(match unified {
+            true => Io::convert_weight_to_cgroup2(weight),
+            false => weight as u64,
+        })
+    };
+    // systemd通过/dev/block/<major>:<minor>识别设备
+    let device_value = |device: &LinuxBlockIoDevice, value: u64| -> Result<Value> {
+        Ok(Value::Struct(vec![
+            Value::Str(format!("/dev/block/{}", common::device_number(device)?)),
+            Value::U64(value),
+        ]))
+    };
+    let (weight_name, device_weight_name) = match unified {
+        true => ("IOWeight", "IODeviceWeight"),
+        false => ("BlockIOWeight", "BlockIODeviceWeight"),
+    };
+    if let Some(w) = block_io.weight {
+        properties.push(property(weight_name, Value::U64(weight(w)?)));
+    }
+    let mut device_weights = Vec::new();
+    for device in block_io.weight_device.iter().flatten() {
+        if let Some(w) = device.weight {
+            device_weights.push(device_value(&device.device, weight(w)?)?);
+        }
+    }
+    if !device_weights.is_empty() {
+        properties.push(property(
+            device_weight_name,
+            Value::Array("(st)".to_owned(), device_weights),
+        ));
+    }
+    let throttles = match unified {
+        true => vec![
+            (&block_io.throttle_read_bps_device, "IOReadBandwidthMax"),
+            (&block_io.throttle_write_bps_device, "IOWriteBandwidthMax"),
+            (&block_io.throttle_read_iops_device, "IOReadIOPSMax"),
+            (&block_io.throttle_write_iops_device, "IOWriteIOPSMax"),
+        ],
+        false => vec![
+            (&block_io.throttle_read_bps_device, "BlockIOReadBandwidth"),
+            (&block_io.throttle_write_bps_device, "BlockIOWriteBandwidth"),
+        ],
+    };
+    for (devices, name) in throttles {
+        let mut values = Vec::new();
+        for device in devices.iter().flatten() {
+            // 0表示不限制
+            let rate = if device.rate == 0 {
+                u64::MAX
+            } else {
+                device.rate
+            };
+            values.push(device_value(&device.device, rate)?);
+        }
+        if !values.is_empty() {
+            properties.push(property(name, Value::Array("(st)".to_owned(), values)));
+        }
+    }
+    Ok(())
+}
+
+//systemd没有对应属性的资源, 由cgroup文件系统上的manager直接写入unit的cgroup:
+//cpuset, 实时调度, hugetlb, 网络, blkio的leaf weight, legacy中的iops限制和memory的部分设置;
+//设备规则是有序的allow/deny列表, 无法用只能追加允许的DeviceAllow表达, 同样直接写入
+fn raw_resources(resources: &LinuxResources, unified: bool) -> LinuxResources {
+    let cpu = resources.cpu.as_ref().map(|cpu| LinuxCpu {
+        realtime_runtime: cpu.realtime_runtime,
+        realtime_period: cpu.realtime_period,
+        cpus: cpu.cpus.clone(),
+        mems: cpu.mems.clone(),
+        ..Default::default()
+    });
+    let memory = resources.memory.as_ref().map(|memory| {
+        let mut raw = LinuxMemory {
+            kernel: memory.kernel,
+            kernel_tcp: memory.kernel_tcp,
+            swappiness: memory.swappiness,
+            disable_oom_killer: memory.disable_oom_killer,
+            use_hierarchy: memory.use_hierarchy,
+            ..Default::default()
+        };
+        if !unified {
+            raw.reservation = memory.reservation;
+            // legacy中没有swap的属性, memsw的写入顺序依赖memory限制,
+            // 因此同时写入与MemoryLimit相同的值
+            if memory.swap.is_some() {
+                raw.swap = memory.swap;
+                raw.limit = memory.limit;
+            }
+        }
+        raw
+    });
+    let block_io = resources.block_io.as_ref().map(|block_io| LinuxBlockIo {
+        leaf_weight: block_io.leaf_weight,
+        weight_device: block_io.weight_device.as_ref().map(|devices| {
+            devices
+                .iter()
+                .filter(|d| d.leaf_weight.is_some())
+                .map(|d| LinuxWeightDevice {
+                    weight: None,
+                    ..d.clone()
+                })
+                .collect()
+        }),
+        throttle_read_iops_device: match unified {
+            true => None,
+            false => block_io.throttle_read_iops_device.clone(),
+        },
+        throttle_write_iops_device: match unified {
+            true => None,
+            false => block_io.throttle_write_iops_device.clone(),
+        },
+        ..Default::default()
+    });
+    LinuxResources {
+        devices: resources.devices.clone(),
+        cpu,
+        memory,
+        pids: None,
+        block_io,
+        hugepage_limits: resources.hugepage_limits.clone(),
+        network: resources.network.clone(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::oci::oci::{LinuxPids, LinuxThrottleDevice};
+    use std::os::unix::net::UnixListener;
+
+    //模拟systemd的总线: 记录收到的方法调用, GetUnit总是返回unit不存在
+    fn mock_systemd(path: &Path) -> std::thread::JoinHandle<Vec<Message>> {
+        let _ = std::fs::remove_file(path);
+        let listener = UnixListener::bind(path).unwrap();
+        std::thread::spawn(move || {
+            let mut calls = Vec::new();
+            for stream in listener.incoming() {
+                let mut stream = stream.unwrap();
+                let mut byte = [0u8; 1];
+                std::io::Read::read_exact(&mut stream, &mut byte).unwrap();
+                let auth = dbus::read_line(&mut stream).unwrap();
+                assert!(auth.starts_with("AUTH EXTERNAL "));
+                std::io::Write::write_all(&mut stream, b"OK 0123456789abcdef\r\n").unwrap();
+                assert_eq!(dbus::read_line(&mut stream).unwrap(), "BEGIN");
+                while let Ok(call) = Message::read(&mut stream) {
+                    let member = call.member.clone().unwrap_or_default();
+                    let mut replies = match member.as_str() {
+                        "Hello" => vec![Message::method_return(
+                            call.serial,
+                            vec![Value::Str(":1.1".to_owned())],
+                        )],
+                        "GetUnit" | "StopUnit" => {
+                            vec![Message::error(call.serial, NO_SUCH_UNIT, "not loaded")]
+                        }
+                        // signal先于返回到达, 客户端需要能够处理
+                        "StartTransientUnit" => vec![
+                            Message::signal(
+                                SYSTEMD_PATH,
+                                MANAGER_INTERFACE,
+                                "JobRemoved",
+                                vec![
+                                    Value::U32(1),
+                                    Value::ObjectPath("/org/freedesktop/systemd1/job/1".to_owned()),
+                                    call.body[0].clone(),
+                                    Value::Str("done".to_owned()),
+                                ],
+                            ),
+                            Message::method_return(
+                                call.serial,
+                                vec![Value::ObjectPath(
+                                    "/org/freedesktop/systemd1/job/1".to_owned(),
+                                )],
+                            ),
+                        ],
+                        _ => vec![Message::method_return(call.serial, vec![])],
+                    };
+                    for reply in replies.iter_mut() {
+                        std::io::Write::write_all(&mut stream, &reply.encode()).unwrap();
+                    }
+                    if member != "Hello" {
+                        calls.push(call);
+                    }
+                }
+                if calls
+                    .iter()
+                    .any(|c| c.member.as_deref() == Some("StopUnit"))
+                {
+                    break;
+                }
+            }
+            calls
+        })
+    }
+
+    #[test]
+    fn test_systemd_manager() {
+        let socket = std::env::temp_dir().join(format!("smog-dbus-{}.sock", std::process::id()));
+        let server = mock_systemd(&socket);
+        let address = format!("unix:path={}", socket.display());
+        let path = unit_path(Some(Path::new(":smog:abc")), "abc", false).unwrap();
+        assert_eq!(path, PathBuf::from("system.slice:smog:abc"));
+        let manager = Manager::with_address(&path, address).unwrap();
+        assert_eq!(manager.unit, "smog-abc.scope");
+
+        manager.add_task(Pid::from_raw(1234)).unwrap();
+        let resources = LinuxResources {
+            cpu: Some(LinuxCpu {
+                shares: Some(1024),
+                quota: Some(50000),
+                period: Some(100000),
+                ..Default::default()
+            }),
+            memory: Some(LinuxMemory {
+                limit: Some(-1),
+                ..Default::default()
+            }),
+            pids: Some(LinuxPids { limit: 32 }),
+            ..Default::default()
+        };
+        let mut conn = manager.connect().unwrap();
+        manager.set_properties(&mut conn, &resources, true).unwrap();
+        drop(conn);
+        manager.remove().unwrap();
+
+        let calls = server.join().unwrap();
+        std::fs::remove_file(&socket).unwrap();
+        let members: Vec<&str> = calls.iter().filter_map(|c| c.member.as_deref()).collect();
+        assert_eq!(
+            members,
+            [
+                "GetUnit",
+                "AddMatch",
+                "Subscribe",
+                "StartTransientUnit",
+                "SetUnitProperties",
+                "StopUnit"
+            ]
+        );
+        let start = &calls[3];
+        assert_eq!(start.signature, "ssa(sv)a(sa(sv))");
+        assert_eq!(start.body[0], Value::Str("smog-abc.scope".to_owned()));
+        let start_properties = match &start.body[2] {
+            Value::Array(_, p) => p,
+            v => panic!("unexpected properties {:?}", v),
+        };
+        assert!(
+            start_properties.contains(&property("Slice", Value::Str("system.slice".to_owned())))
+        );
+        assert!(start_properties.contains(&property(
+            "PIDs",
+            Value::Array("u".to_owned(), vec![Value::U32(1234)])
+        )));
+        assert_eq!(
+            calls[4].body[2],
+            Value::Array(
+                "(sv)".to_owned(),
+                vec![
+                    property("CPUWeight", Value::U64(39)),
+                    property("CPUQuotaPeriodUSec", Value::U64(100000)),
+                    property("CPUQuotaPerSecUSec", Value::U64(500000)),
+                    property("MemoryMax", Value::U64(u64::MAX)),
+                    property("TasksMax", Value::U64(32)),
+                ]
+            )
+        );
+    }
+
+    #[test]
+    fn test_legacy_resources() {
+        let device = LinuxBlockIoDevice {
+            major: 8,
+            minor: 0,
+            path: None,
+        };
+        let resources = LinuxResources {
+            cpu: Some(LinuxCpu {
+                shares: Some(512),
+                quota: Some(15500),
+                cpus: Some("0".to_owned()),
+                ..Default::default()
+            }),
+            memory: Some(LinuxMemory {
+                limit: Some(1 << 20),
+                swap: Some(2 << 20),
+                ..Default::default()
+            }),
+            block_io: Some(LinuxBlockIo {
+                weight: Some(500),
+                weight_device: Some(vec![LinuxWeightDevice {
+                    device: device.clone(),
+                    weight: Some(100),
+                    leaf_weight: Some(200),
+                }]),
+                throttle_read_iops_device: Some(vec![LinuxThrottleDevice { device, rate: 10 }]),
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+        let block_device = |rate| {
+            Value::Array(
+                "(st)".to_owned(),
+                vec![Value::Struct(vec![
+                    Value::Str("/dev/block/8:0".to_owned()),
+                    Value::U64(rate),
+                ])],
+            )
+        };
+        // 每秒155ms的配额向上取整到160ms
+        assert_eq!(
+            properties(&resources, false).unwrap(),
+            vec![
+                property("CPUShares", Value::U64(512)),
+                property("CPUQuotaPerSecUSec", Value::U64(160000)),
+                property("MemoryLimit", Value::U64(1 << 20)),
+                property("BlockIOWeight", Value::U64(500)),
+                property("BlockIODeviceWeight", block_device(100)),
+            ]
+        );
+
+        let raw = raw_resources(&resources, false);
+        let cpu = raw.cpu.unwrap();
+        assert_eq!((cpu.shares, cpu.quota), (None, None));
+        assert_eq!(cpu.cpus.as_deref(), Some("0"));
+        let block_io = raw.block_io.unwrap();
+        assert_eq!(block_io.weight, None);
+        let weight_device = &block_io.weight_device.unwrap()[0];
+        assert_eq!(
+            (weight_device.weight, weight_device.leaf_weight),
+            (None, Some(200))
+        );
+        assert!(block_io.throttle_read_iops_device.is_some());
+        // unified中iops限制和swap都通过属性设置
+        let raw = raw_resources(&resources, true);
+        assert_eq!(raw.memory.unwrap().swap, None);
+        assert_eq!(raw.block_io.unwrap().throttle_read_iops_device, None);
+    }
+
+    #[test]
+    fn test_unit_path() {
+        assert_eq!(
+            unit_path(None, "abc", true).unwrap(),
+            PathBuf::from("user.slice:smog:abc")
+        );
+        assert_eq!(
+            unit_path(Some(Path::new("machine.slice:docker:abc")), "abc", false).unwrap(),
+            PathBuf::from("machine.slice:docker:abc")
+        );
+        assert!(unit_path(Some(Path::new("/a/b")), "abc", false).is_err());
+        assert!(unit_path(Some(Path::new("machine:smog:abc")), "abc", false).is_err());
+    }
+}
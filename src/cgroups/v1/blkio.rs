@@ -0,0 +1,100 @@
+use super::subsystem::SubSystem;
+use crate::cgroups::common::{self, ControllerOpt};
+use crate::oci::oci::{LinuxBlockIo, LinuxThrottleDevice};
+use anyhow::{bail, Result};
+use std::path::{Path, PathBuf};
+
+const CGROUP_BLKIO_WEIGHT: &str = "blkio.weight";
+const CGROUP_BLKIO_WEIGHT_DEVICE: &str = "blkio.weight_device";
+const CGROUP_BLKIO_BFQ_WEIGHT: &str = "blkio.bfq.weight";
+const CGROUP_BLKIO_BFQ_WEIGHT_DEVICE: &str = "blkio.bfq.weight_device";
+const CGROUP_BLKIO_LEAF_WEIGHT: &str = "blkio.leaf_weight";
+const CGROUP_BLKIO_LEAF_WEIGHT_DEVICE: &str = "blkio.leaf_weight_device";
+const CGROUP_BLKIO_THROTTLE_READ_BPS: &str = "blkio.throttle.read_bps_device";
+const CGROUP_BLKIO_THROTTLE_WRITE_BPS: &str = "blkio.throttle.write_bps_device";
+const CGROUP_BLKIO_THROTTLE_READ_IOPS: &str = "blkio.throttle.read_iops_device";
+const CGROUP_BLKIO_THROTTLE_WRITE_IOPS: &str = "blkio.throttle.write_iops_device";
+
+pub struct Blkio {}
+
+impl SubSystem for Blkio {
+    fn apply(controller_opt: &ControllerOpt, cgroup_path: &Path) -> Result<()> {
+        if let Some(block_io) = &controller_opt.resources.block_io {
+            Self::apply(block_io, cgroup_path)?;
+        }
+        Ok(())
+    }
+}
+
+impl Blkio {
+    fn apply(block_io: &LinuxBlockIo, path: &Path) -> Result<()> {
+        if let Some(weight) = block_io.weight {
+            let file = Self::weight_file(path, CGROUP_BLKIO_WEIGHT, CGROUP_BLKIO_BFQ_WEIGHT)?;
+            common::write_cgroup_file(file, weight)?;
+        }
+        if let Some(leaf_weight) = block_io.leaf_weight {
+            let file = Self::weight_file(path, CGROUP_BLKIO_LEAF_WEIGHT, CGROUP_BLKIO_BFQ_WEIGHT)?;
+            common::write_cgroup_file(file, leaf_weight)?;
+        }
+        for device in block_io.weight_device.iter().flatten() {
+            let number = common::device_number(&device.device)?;
+            if let Some(weight) = device.weight {
+                let file = Self::weight_file(
+                    path,
+                    CGROUP_BLKIO_WEIGHT_DEVICE,
+                    CGROUP_BLKIO_BFQ_WEIGHT_DEVICE,
+                )?;
+                common::write_cgroup_file_str(file, &format!("{} {}", number, weight))?;
+            }
+            if let Some(leaf_weight) = device.leaf_weight {
+                let file = Self::weight_file(
+                    path,
+                    CGROUP_BLKIO_LEAF_WEIGHT_DEVICE,
+                    CGROUP_BLKIO_BFQ_WEIGHT_DEVICE,
+                )?;
+                common::write_cgroup_file_str(file, &format!("{} {}", number, leaf_weight))?;
+            }
+        }
+        for (devices, file) in [
+            (
+                &block_io.throttle_read_bps_device,
+                CGROUP_BLKIO_THROTTLE_READ_BPS,
+            ),
+            (
+                &block_io.throttle_write_bps_device,
+                CGROUP_BLKIO_THROTTLE_WRITE_BPS,
+            ),
+            (
+                &block_io.throttle_read_iops_device,
+                CGROUP_BLKIO_THROTTLE_READ_IOPS,
+            ),
+            (
+                &block_io.throttle_write_iops_device,
+                CGROUP_BLKIO_THROTTLE_WRITE_IOPS,
+            ),
+        ] {
+            Self::set_throttle(path.join(file), devices.as_deref().unwrap_or_default())?;
+        }
+        Ok(())
+    }
+
+    //使用cfq调度器时有blkio.weight和blkio.leaf_weight, 使用bfq时只有blkio.bfq.weight,
+    //leaf_weight也退回到bfq的weight文件
+    fn weight_file(path: &Path, file: &str, bfq_file: &str) -> Result<PathBuf> {
+        for f in [file, bfq_file] {
+            if path.join(f).exists() {
+                return Ok(path.join(f));
+            }
+        }
+        bail!("{} is not supported by the kernel", file)
+    }
+
+    // 0表示不限制
+    fn set_throttle(file: PathBuf, devices: &[LinuxThrottleDevice]) -> Result<()> {
+        for device in devices {
+            let line = format!("{} {}", common::device_number(&device.device)?, device.rate);
+            common::write_cgroup_file_str(&file, &line)?;
+        }
+        Ok(())
+    }
+}
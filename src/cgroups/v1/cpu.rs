@@ -1 +1,55 @@
-struct CPU {}
+use super::subsystem::SubSystem;
+use crate::cgroups::common::{self, ControllerOpt};
+use crate::oci::oci::LinuxCpu;
+use anyhow::Result;
+use std::path::Path;
+
+const CGROUP_CPU_SHARES: &str = "cpu.shares";
+const CGROUP_CPU_QUOTA: &str = "cpu.cfs_quota_us";
+const CGROUP_CPU_PERIOD: &str = "cpu.cfs_period_us";
+const CGROUP_CPU_RT_RUNTIME: &str = "cpu.rt_runtime_us";
+const CGROUP_CPU_RT_PERIOD: &str = "cpu.rt_period_us";
+
+pub struct Cpu {}
+
+impl SubSystem for Cpu {
+    fn apply(controller_opt: &ControllerOpt, cgroup_path: &Path) -> Result<()> {
+        if let Some(cpu) = &controller_opt.resources.cpu {
+            Self::apply(cpu, cgroup_path)?;
+        }
+        Ok(())
+    }
+}
+
+impl Cpu {
+    fn apply(cpu: &LinuxCpu, path: &Path) -> Result<()> {
+        if let Some(shares) = cpu.shares {
+            if shares != 0 {
+                common::write_cgroup_file(path.join(CGROUP_CPU_SHARES), shares)?;
+            }
+        }
+        // quota需要在period之后写入, 否则会按照旧的period检查quota是否合法
+        if let Some(period) = cpu.period {
+            if period != 0 {
+                common::write_cgroup_file(path.join(CGROUP_CPU_PERIOD), period)?;
+            }
+        }
+        if let Some(quota) = cpu.quota {
+            // 小于等于0都表示不限制
+            let quota = if quota > 0 { quota } else { -1 };
+            common::write_cgroup_file(path.join(CGROUP_CPU_QUOTA), quota)?;
+        }
+        // 同样需要先写period
+        if let Some(rt_period) = cpu.realtime_period {
+            if rt_period != 0 {
+                common::write_cgroup_file(path.join(CGROUP_CPU_RT_PERIOD), rt_period)?;
+            }
+        }
+        if let Some(rt_runtime) = cpu.realtime_runtime {
+            if rt_runtime != 0 {
+                common::write_cgroup_file(path.join(CGROUP_CPU_RT_RUNTIME), rt_runtime)?;
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,56 @@
+use super::subsystem::SubSystem;
+use crate::cgroups::common::{self, ControllerOpt};
+use anyhow::{Context, Result};
+use std::fs;
+use std::path::Path;
+
+const CGROUP_CPUSET_CPUS: &str = "cpuset.cpus";
+const CGROUP_CPUSET_MEMS: &str = "cpuset.mems";
+
+pub struct CpuSet {}
+
+impl SubSystem for CpuSet {
+    fn apply(controller_opt: &ControllerOpt, cgroup_path: &Path) -> Result<()> {
+        if let Some(cpu) = &controller_opt.resources.cpu {
+            if let Some(cpus) = &cpu.cpus {
+                Self::apply(cpus, CGROUP_CPUSET_CPUS, cgroup_path)?;
+            }
+            if let Some(mems) = &cpu.mems {
+                Self::apply(mems, CGROUP_CPUSET_MEMS, cgroup_path)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl CpuSet {
+    //请求的cpu或内存节点必须在父cgroup的范围内
+    fn apply(value: &str, file: &str, path: &Path) -> Result<()> {
+        if let Some(parent) = path.parent() {
+            let available = fs::read_to_string(parent.join(file))
+                .with_context(|| format!("failed to read {:?}", parent.join(file)))?;
+            common::check_cpuset(value, &available)
+                .with_context(|| format!("invalid {} {:?}", file, value))?;
+        }
+        common::write_cgroup_file_str(path.join(file), value)
+    }
+
+    //新建的cpuset cgroup中cpus和mems为空, 此时无法加入进程, 需要从父cgroup继承
+    pub fn inherit_from_parent(path: &Path) -> Result<()> {
+        let parent = match path.parent() {
+            Some(parent) => parent,
+            None => return Ok(()),
+        };
+        for file in [CGROUP_CPUSET_CPUS, CGROUP_CPUSET_MEMS] {
+            let current = fs::read_to_string(path.join(file))
+                .with_context(|| format!("failed to read {:?}", path.join(file)))?;
+            if !current.trim().is_empty() {
+                continue;
+            }
+            let value = fs::read_to_string(parent.join(file))
+                .with_context(|| format!("failed to read {:?}", parent.join(file)))?;
+            common::write_cgroup_file_str(path.join(file), value.trim())?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,73 @@
+use super::subsystem::SubSystem;
+use crate::cgroups::common::{self, ControllerOpt};
+use crate::oci::oci::LinuxDeviceCgroup;
+use anyhow::{Context, Result};
+use std::path::Path;
+
+const CGROUP_DEVICES_ALLOW: &str = "devices.allow";
+const CGROUP_DEVICES_DENY: &str = "devices.deny";
+
+pub struct Devices {}
+
+impl SubSystem for Devices {
+    fn apply(controller_opt: &ControllerOpt, cgroup_path: &Path) -> Result<()> {
+        if let Some(devices) = &controller_opt.resources.devices {
+            let defaults = common::default_allowed_devices();
+            for rule in devices.iter().chain(defaults.iter()) {
+                Self::apply(rule, cgroup_path)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Devices {
+    //规则按顺序写入, 写入a会清空之前的规则
+    fn apply(rule: &LinuxDeviceCgroup, path: &Path) -> Result<()> {
+        let file = if rule.allow {
+            CGROUP_DEVICES_ALLOW
+        } else {
+            CGROUP_DEVICES_DENY
+        };
+        let entry = Self::entry(rule);
+        common::write_cgroup_file_str(path.join(file), &entry)
+            .with_context(|| format!("failed to write {:?} to {}", entry, file))
+    }
+
+    //格式为"type major:minor access", 没有指定的major和minor用*表示
+    fn entry(rule: &LinuxDeviceCgroup) -> String {
+        let number = |n: Option<i64>| match n {
+            Some(n) if n >= 0 => n.to_string(),
+            _ => "*".to_owned(),
+        };
+        format!(
+            "{} {}:{} {}",
+            rule.typ.as_deref().unwrap_or("a"),
+            number(rule.major),
+            number(rule.minor),
+            rule.access.as_deref().unwrap_or("rwm")
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    fn test_entry() {
+        let rule = LinuxDeviceCgroup {
+            allow: false,
+            access: Some("rwm".to_owned()),
+            ..Default::default()
+        };
+        assert_eq!(Devices::entry(&rule), "a *:* rwm");
+        let rule = LinuxDeviceCgroup {
+            allow: true,
+            typ: Some("c".to_owned()),
+            major: Some(136),
+            minor: Some(-1),
+            access: Some("rw".to_owned()),
+        };
+        assert_eq!(Devices::entry(&rule), "c 136:* rw");
+    }
+}
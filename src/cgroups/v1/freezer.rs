@@ -0,0 +1,42 @@
+use crate::cgroups::common;
+use crate::cgroups::FreezerState;
+use anyhow::{bail, Context, Result};
+use std::fs;
+use std::path::Path;
+use std::thread;
+use std::time::{Duration, Instant};
+
+const FREEZER_STATE: &str = "freezer.state";
+const FREEZE_TIMEOUT: Duration = Duration::from_secs(10);
+
+pub struct Freezer {}
+
+impl Freezer {
+    //v1的freezer.state在冻结过程中为FREEZING, 不支持事件通知, 只能轮询直到读出目标状态
+    pub fn apply(state: FreezerState, path: &Path) -> Result<()> {
+        let value = match state {
+            FreezerState::Frozen => "FROZEN",
+            FreezerState::Thawed => "THAWED",
+        };
+        let state_path = path.join(FREEZER_STATE);
+        let deadline = Instant::now() + FREEZE_TIMEOUT;
+        loop {
+            // 有进程在冻结过程中fork时内核会放弃冻结, 需要重新写入
+            common::write_cgroup_file_str(&state_path, value)
+                .with_context(|| format!("failed to write {} to {:?}", value, state_path))?;
+            let current = fs::read_to_string(&state_path)
+                .with_context(|| format!("failed to read {:?}", state_path))?;
+            if current.trim() == value {
+                return Ok(());
+            }
+            if Instant::now() > deadline {
+                bail!(
+                    "timed out waiting for cgroup {:?} to become {:?}",
+                    path,
+                    state
+                );
+            }
+            thread::sleep(Duration::from_millis(10));
+        }
+    }
+}
@@ -1,37 +1,65 @@
-use super::subsystem::{SubSystemType, SUBSYSTEMLIST};
-use crate::cgroups::common::ControllerOpt;
-use crate::cgroups::CgroupManager;
-use crate::cgroups::SMOG;
+use super::blkio::Blkio;
+use super::cpu::Cpu;
+use super::cpuset::CpuSet;
+use super::devices::Devices;
+use super::freezer::Freezer;
+use super::hugetlb::HugeTlb;
+use super::memory::Memory;
+use super::network::{NetCls, NetPrio};
+use super::oom::EventFdNotifier;
+use super::pids::Pids;
+use super::stats;
+use super::subsystem::{SubSystem, SubSystemType, SUBSYSTEMLIST};
+use crate::cgroups::common::{self, ControllerOpt, CGROUP_PROCS};
+use crate::cgroups::stats::Stats;
+use crate::cgroups::{CgroupManager, FreezerState, OomNotifier};
 use crate::utils::fs;
-use anyhow::{anyhow, bail, Result};
+use anyhow::{anyhow, Context, Result};
 use nix::unistd::Pid;
 use procfs::process::Process;
 use std::path::Path;
 use std::{collections::HashMap, path::PathBuf};
+
 pub struct Manager {
+    cgroups_path: PathBuf,
     subsystems: HashMap<SubSystemType, PathBuf>,
 }
 
 impl Manager {
-    pub fn new(container_id: &str) -> Manager {
+    //cgroups_path是相对路径, 绝对路径join到挂载点上会替换掉挂载点
+    pub fn new(cgroups_path: &Path) -> Manager {
         let mut subsystems: HashMap<SubSystemType, PathBuf> = HashMap::new();
-        let cgroups_path = PathBuf::from(format!("/{}/{}", SMOG, container_id));
         for subsystem in SUBSYSTEMLIST {
-            if let Ok(subsystem_path) = Self::get_subsystem_path(&cgroups_path, &subsystem) {
+            // 没有挂载的subsystem直接跳过
+            if let Ok(subsystem_path) = Self::get_subsystem_path(cgroups_path, subsystem) {
                 subsystems.insert(subsystem.clone(), subsystem_path);
-            } else {
-                println!("cgroup {} not supported on this system", subsystem);
             }
         }
         Self {
-            subsystems: subsystems,
+            cgroups_path: cgroups_path.to_path_buf(),
+            subsystems,
         }
     }
 
-    fn add_task() {}
+    //逐级创建cgroup目录, cpuset的每一级都需要从父cgroup继承cpus和mems
+    fn create_cgroup(&self, subsystem: &SubSystemType, path: &Path) -> Result<()> {
+        if *subsystem != SubSystemType::CpuSet {
+            return fs::create_dir_all(path);
+        }
+        let mount_point = get_subsystem_mount_point(subsystem)?;
+        let mut current = mount_point;
+        for component in self.cgroups_path.components() {
+            current = current.join(component);
+            if !current.exists() {
+                fs::create_dir_all(&current)?;
+            }
+            CpuSet::inherit_from_parent(&current)?;
+        }
+        Ok(())
+    }
 
     fn get_subsystem_path(path: &Path, subsystem: &SubSystemType) -> Result<PathBuf> {
-        let mount_point = get_subsystem_mount_point(subsystem).unwrap();
+        let mount_point = get_subsystem_mount_point(subsystem)?;
         let p = mount_point.join(path);
         Ok(p)
     }
@@ -74,17 +102,66 @@ pub fn get_subsystem_mount_point(subsystem: &SubSystemType) -> Result<PathBuf> {
 
 impl CgroupManager for Manager {
     fn add_task(&self, pid: Pid) -> Result<()> {
-        //self.create_unified_cgroup(pid)?;
+        for (subsystem, path) in self.subsystems.iter() {
+            self.create_cgroup(subsystem, path)?;
+            common::write_cgroup_file(path.join(CGROUP_PROCS), pid)
+                .with_context(|| format!("failed to add {} to cgroup {:?}", pid, path))?;
+        }
         Ok(())
     }
 
     fn apply(&self, controller_opt: &ControllerOpt) -> Result<()> {
-        // for controller in SUBSYSTEMLIST {
-        //     match controller {
-        //         SubSystemType::Cpu => Cpu::apply(controller_opt, &self.full_path)?,
-        //         _ => {}
-        //     }
-        // }
+        for (subsystem, path) in self.subsystems.iter() {
+            match subsystem {
+                SubSystemType::Cpu => Cpu::apply(controller_opt, path)?,
+                SubSystemType::CpuSet => CpuSet::apply(controller_opt, path)?,
+                SubSystemType::Blkio => Blkio::apply(controller_opt, path)?,
+                SubSystemType::Devices => Devices::apply(controller_opt, path)?,
+                SubSystemType::Memory => Memory::apply(controller_opt, path)?,
+                SubSystemType::Pids => Pids::apply(controller_opt, path)?,
+                SubSystemType::HugeTlb => HugeTlb::apply(controller_opt, path)?,
+                SubSystemType::NetCls => NetCls::apply(controller_opt, path)?,
+                SubSystemType::NetPrio => NetPrio::apply(controller_opt, path)?,
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+
+    //每个subsystem中的进程都相同, 读取任意一个即可
+    fn get_all_pids(&self) -> Result<Vec<Pid>> {
+        let path = SUBSYSTEMLIST
+            .iter()
+            .find_map(|s| self.subsystems.get(s))
+            .ok_or_else(|| anyhow!("no cgroup subsystem available"))?;
+        common::get_all_pids(path)
+    }
+
+    fn freeze(&self, state: FreezerState) -> Result<()> {
+        let path = self
+            .subsystems
+            .get(&SubSystemType::Freezer)
+            .ok_or_else(|| anyhow!("freezer cgroup is not available"))?;
+        Freezer::apply(state, path)
+    }
+
+    fn stats(&self) -> Result<Stats> {
+        stats::stats(&self.subsystems)
+    }
+
+    fn oom_notifier(&self) -> Result<Box<dyn OomNotifier>> {
+        let path = self
+            .subsystems
+            .get(&SubSystemType::Memory)
+            .ok_or_else(|| anyhow!("memory cgroup is not available"))?;
+        Ok(Box::new(EventFdNotifier::new(path)?))
+    }
+
+    fn remove(&self) -> Result<()> {
+        for subsystem in self.subsystems.keys() {
+            let mount_point = get_subsystem_mount_point(subsystem)?;
+            common::remove_cgroup(&mount_point, &self.cgroups_path)?;
+        }
         Ok(())
     }
 }
@@ -1 +1,122 @@
-struct Memory {}
+use super::subsystem::SubSystem;
+use crate::cgroups::common::{self, ControllerOpt};
+use crate::cgroups::stats;
+use crate::oci::oci::LinuxMemory;
+use anyhow::{bail, Result};
+use std::path::Path;
+
+const CGROUP_MEMORY_LIMIT: &str = "memory.limit_in_bytes";
+const CGROUP_MEMORY_SWAP_LIMIT: &str = "memory.memsw.limit_in_bytes";
+const CGROUP_MEMORY_RESERVATION: &str = "memory.soft_limit_in_bytes";
+const CGROUP_KERNEL_MEMORY_LIMIT: &str = "memory.kmem.limit_in_bytes";
+const CGROUP_KERNEL_TCP_MEMORY_LIMIT: &str = "memory.kmem.tcp.limit_in_bytes";
+const CGROUP_MEMORY_SWAPPINESS: &str = "memory.swappiness";
+const CGROUP_MEMORY_OOM_CONTROL: &str = "memory.oom_control";
+const CGROUP_MEMORY_USE_HIERARCHY: &str = "memory.use_hierarchy";
+
+pub struct Memory {}
+
+impl SubSystem for Memory {
+    fn apply(controller_opt: &ControllerOpt, cgroup_path: &Path) -> Result<()> {
+        if let Some(memory) = &controller_opt.resources.memory {
+            Self::apply(memory, cgroup_path)?;
+        }
+        Ok(())
+    }
+}
+
+impl Memory {
+    fn apply(memory: &LinuxMemory, path: &Path) -> Result<()> {
+        Self::set_limits(memory, path)?;
+        if let Some(reservation) = memory.reservation {
+            common::write_cgroup_file(path.join(CGROUP_MEMORY_RESERVATION), reservation)?;
+        }
+        if let Some(kernel) = memory.kernel {
+            common::write_cgroup_file(path.join(CGROUP_KERNEL_MEMORY_LIMIT), kernel)?;
+        }
+        if let Some(kernel_tcp) = memory.kernel_tcp {
+            common::write_cgroup_file(path.join(CGROUP_KERNEL_TCP_MEMORY_LIMIT), kernel_tcp)?;
+        }
+        if let Some(swappiness) = memory.swappiness {
+            if swappiness > 100 {
+                bail!("invalid memory swappiness {}, must be in 0-100", swappiness);
+            }
+            common::write_cgroup_file(path.join(CGROUP_MEMORY_SWAPPINESS), swappiness)?;
+        }
+        //显式写入0, update时才能重新启用oom killer
+        if let Some(disable_oom_killer) = memory.disable_oom_killer {
+            let value = if disable_oom_killer { "1" } else { "0" };
+            common::write_cgroup_file_str(path.join(CGROUP_MEMORY_OOM_CONTROL), value)?;
+        }
+        if let Some(use_hierarchy) = memory.use_hierarchy {
+            let value = if use_hierarchy { "1" } else { "0" };
+            common::write_cgroup_file_str(path.join(CGROUP_MEMORY_USE_HIERARCHY), value)?;
+        }
+        Ok(())
+    }
+
+    //memory.memsw.limit_in_bytes不能小于memory.limit_in_bytes,
+    //需要根据新旧值决定两者的写入顺序
+    fn set_limits(memory: &LinuxMemory, path: &Path) -> Result<()> {
+        let swap = match memory.swap {
+            Some(swap) => swap,
+            None => {
+                if let Some(limit) = memory.limit {
+                    common::write_cgroup_file(path.join(CGROUP_MEMORY_LIMIT), limit)?;
+                }
+                return Ok(());
+            }
+        };
+        let limit = memory.limit.unwrap_or(-1);
+        if limit != -1 && swap != -1 && swap < limit {
+            bail!(
+                "memory+swap limit {} should be larger than memory limit {}",
+                swap,
+                limit
+            );
+        }
+        // 内核没有开启swap统计时没有memsw文件
+        if !path.join(CGROUP_MEMORY_SWAP_LIMIT).exists() {
+            bail!("swap limit is not supported by the kernel");
+        }
+        let current_swap = stats::parse_single_value(&path.join(CGROUP_MEMORY_SWAP_LIMIT))?;
+        if swap == -1 || swap as u64 > current_swap {
+            common::write_cgroup_file(path.join(CGROUP_MEMORY_SWAP_LIMIT), swap)?;
+            common::write_cgroup_file(path.join(CGROUP_MEMORY_LIMIT), limit)?;
+        } else {
+            common::write_cgroup_file(path.join(CGROUP_MEMORY_LIMIT), limit)?;
+            common::write_cgroup_file(path.join(CGROUP_MEMORY_SWAP_LIMIT), swap)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    fn test_swap_smaller_than_limit() {
+        let memory = LinuxMemory {
+            limit: Some(2048),
+            swap: Some(1024),
+            ..Default::default()
+        };
+        let err = Memory::set_limits(&memory, Path::new("/nonexistent")).unwrap_err();
+        assert!(err.to_string().contains("should be larger"));
+    }
+
+    #[test]
+    fn test_enable_oom_killer() {
+        let dir = std::env::temp_dir().join(format!("smog-memory-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join(CGROUP_MEMORY_OOM_CONTROL), "").unwrap();
+        let memory = LinuxMemory {
+            disable_oom_killer: Some(false),
+            ..Default::default()
+        };
+        Memory::apply(&memory, &dir).unwrap();
+        let content = std::fs::read_to_string(dir.join(CGROUP_MEMORY_OOM_CONTROL)).unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(content, "0");
+    }
+}
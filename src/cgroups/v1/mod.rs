@@ -1,2 +1,13 @@
+mod blkio;
+mod cpu;
+mod cpuset;
+mod devices;
+mod freezer;
+mod hugetlb;
 pub mod manager;
+mod memory;
+mod network;
+mod oom;
+mod pids;
+mod stats;
 mod subsystem;
@@ -0,0 +1,45 @@
+use super::subsystem::SubSystem;
+use crate::cgroups::common::{self, ControllerOpt};
+use anyhow::Result;
+use std::path::Path;
+
+const CGROUP_NET_CLS_CLASSID: &str = "net_cls.classid";
+const CGROUP_NET_PRIO_IFPRIOMAP: &str = "net_prio.ifpriomap";
+
+pub struct NetCls {}
+
+impl SubSystem for NetCls {
+    fn apply(controller_opt: &ControllerOpt, cgroup_path: &Path) -> Result<()> {
+        if let Some(class_id) = controller_opt
+            .resources
+            .network
+            .as_ref()
+            .and_then(|n| n.class_id)
+        {
+            common::write_cgroup_file(cgroup_path.join(CGROUP_NET_CLS_CLASSID), class_id)?;
+        }
+        Ok(())
+    }
+}
+
+pub struct NetPrio {}
+
+impl SubSystem for NetPrio {
+    fn apply(controller_opt: &ControllerOpt, cgroup_path: &Path) -> Result<()> {
+        if let Some(priorities) = controller_opt
+            .resources
+            .network
+            .as_ref()
+            .and_then(|n| n.priorities.as_ref())
+        {
+            // 每次只能写入一个网卡的优先级
+            for p in priorities {
+                common::write_cgroup_file_str(
+                    cgroup_path.join(CGROUP_NET_PRIO_IFPRIOMAP),
+                    &format!("{} {}", p.name, p.priority),
+                )?;
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,59 @@
+use crate::cgroups::common;
+use crate::cgroups::OomNotifier;
+use anyhow::{Context, Result};
+use nix::sys::eventfd::{eventfd, EfdFlags};
+use nix::unistd::{close, read};
+use std::fs::File;
+use std::os::unix::io::{AsRawFd, RawFd};
+use std::path::{Path, PathBuf};
+
+const OOM_CONTROL: &str = "memory.oom_control";
+const EVENT_CONTROL: &str = "cgroup.event_control";
+
+//把eventfd和memory.oom_control注册到cgroup.event_control, 发生OOM时内核会写eventfd
+pub struct EventFdNotifier {
+    efd: RawFd,
+    // 注册期间需要保持oom_control打开
+    oom_control: File,
+    path: PathBuf,
+}
+
+impl EventFdNotifier {
+    pub fn new(cgroup_path: &Path) -> Result<Self> {
+        let oom_control = File::open(cgroup_path.join(OOM_CONTROL))
+            .with_context(|| format!("failed to open {:?}", cgroup_path.join(OOM_CONTROL)))?;
+        let efd = eventfd(0, EfdFlags::EFD_CLOEXEC)?;
+        let notifier = Self {
+            efd,
+            oom_control,
+            path: cgroup_path.to_path_buf(),
+        };
+        common::write_cgroup_file_str(
+            cgroup_path.join(EVENT_CONTROL),
+            &format!("{} {}", efd, notifier.oom_control.as_raw_fd()),
+        )?;
+        Ok(notifier)
+    }
+}
+
+impl OomNotifier for EventFdNotifier {
+    fn as_raw_fd(&self) -> RawFd {
+        self.efd
+    }
+
+    fn read(&mut self) -> Result<u64> {
+        let mut buf = [0u8; 8];
+        read(self.efd, &mut buf)?;
+        // cgroup被删除时eventfd同样会被通知
+        if !self.path.exists() {
+            return Ok(0);
+        }
+        Ok(u64::from_ne_bytes(buf))
+    }
+}
+
+impl Drop for EventFdNotifier {
+    fn drop(&mut self) {
+        let _ = close(self.efd);
+    }
+}
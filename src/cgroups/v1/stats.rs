@@ -0,0 +1,151 @@
+use super::subsystem::SubSystemType;
+use crate::cgroups::stats::{
+    self, BlkioEntry, BlkioStats, CpuStats, HugeTlbStats, MemoryEntry, MemoryStats, PidsStats,
+    Stats,
+};
+use anyhow::{bail, Context, Result};
+use nix::unistd::{sysconf, SysconfVar};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+const NANOS_PER_SECOND: u64 = 1_000_000_000;
+
+//只读取已经创建了cgroup目录的subsystem
+pub fn stats(subsystems: &HashMap<SubSystemType, PathBuf>) -> Result<Stats> {
+    let path_of = |subsystem: &SubSystemType| subsystems.get(subsystem).filter(|p| p.exists());
+    let mut stats = Stats::default();
+    if let Some(path) = path_of(&SubSystemType::CpuAcct) {
+        cpuacct_stats(path, &mut stats.cpu)?;
+    }
+    if let Some(path) = path_of(&SubSystemType::Cpu) {
+        let values = stats::parse_flat_keyed(&path.join("cpu.stat"))?;
+        let get = |key: &str| values.get(key).copied().unwrap_or_default();
+        stats.cpu.throttling.periods = get("nr_periods");
+        stats.cpu.throttling.throttled_periods = get("nr_throttled");
+        stats.cpu.throttling.throttled_time = get("throttled_time");
+    }
+    if let Some(path) = path_of(&SubSystemType::Memory) {
+        stats.memory = memory_stats(path)?;
+    }
+    if let Some(path) = path_of(&SubSystemType::Pids) {
+        stats.pids = PidsStats {
+            current: stats::parse_single_value(&path.join("pids.current"))?,
+            limit: stats::parse_single_value(&path.join("pids.max"))?,
+        };
+    }
+    if let Some(path) = path_of(&SubSystemType::Blkio) {
+        stats.blkio = BlkioStats {
+            io_service_bytes_recursive: blkio_entries(&fs::read_to_string(
+                path.join("blkio.throttle.io_service_bytes_recursive"),
+            )?)?,
+            io_serviced_recursive: blkio_entries(&fs::read_to_string(
+                path.join("blkio.throttle.io_serviced_recursive"),
+            )?)?,
+        };
+    }
+    if let Some(path) = path_of(&SubSystemType::HugeTlb) {
+        stats.hugetlb = hugetlb_stats(path)?;
+    }
+    Ok(stats)
+}
+
+//cpuacct.usage单位是纳秒, cpuacct.stat单位是USER_HZ
+fn cpuacct_stats(path: &Path, cpu: &mut CpuStats) -> Result<()> {
+    cpu.usage.total = stats::parse_single_value(&path.join("cpuacct.usage"))?;
+    cpu.usage.percpu = fs::read_to_string(path.join("cpuacct.usage_percpu"))?
+        .split_whitespace()
+        .map(|v| v.parse())
+        .collect::<Result<_, _>>()
+        .context("invalid cpuacct.usage_percpu")?;
+    let ticks = match sysconf(SysconfVar::CLK_TCK)? {
+        Some(ticks) if ticks > 0 => ticks as u64,
+        _ => bail!("failed to get clock ticks"),
+    };
+    let values = stats::parse_flat_keyed(&path.join("cpuacct.stat"))?;
+    cpu.usage.user = values.get("user").copied().unwrap_or_default() * NANOS_PER_SECOND / ticks;
+    cpu.usage.kernel = values.get("system").copied().unwrap_or_default() * NANOS_PER_SECOND / ticks;
+    Ok(())
+}
+
+fn memory_stats(path: &Path) -> Result<MemoryStats> {
+    let entry = |prefix: &str| -> Result<MemoryEntry> {
+        // 内核没有开启swap统计时不存在memsw文件
+        if !path.join(format!("{}.usage_in_bytes", prefix)).exists() {
+            return Ok(MemoryEntry::default());
+        }
+        let value =
+            |name: &str| stats::parse_single_value(&path.join(format!("{}.{}", prefix, name)));
+        Ok(MemoryEntry {
+            limit: value("limit_in_bytes")?,
+            usage: value("usage_in_bytes")?,
+            max: value("max_usage_in_bytes")?,
+            failcnt: value("failcnt")?,
+        })
+    };
+    Ok(MemoryStats {
+        usage: entry("memory")?,
+        swap: entry("memory.memsw")?,
+        raw: stats::parse_flat_keyed(&path.join("memory.stat"))?,
+    })
+}
+
+//blkio文件每行为"8:0 Read 4096", 最后一行为"Total 4096"
+fn blkio_entries(content: &str) -> Result<Vec<BlkioEntry>> {
+    let mut entries = Vec::new();
+    for line in content.lines() {
+        let fields: Vec<&str> = line.split_whitespace().collect();
+        if fields.len() != 3 {
+            continue;
+        }
+        let (major, minor) = stats::parse_device_number(fields[0])?;
+        entries.push(BlkioEntry {
+            major,
+            minor,
+            op: fields[1].to_owned(),
+            value: fields[2]
+                .parse()
+                .with_context(|| format!("invalid blkio entry {:?}", line))?,
+        });
+    }
+    Ok(entries)
+}
+
+fn hugetlb_stats(path: &Path) -> Result<HashMap<String, HugeTlbStats>> {
+    let mut hugetlb = HashMap::new();
+    for entry in fs::read_dir(path)? {
+        let name = entry?.file_name().to_string_lossy().into_owned();
+        let size = match name
+            .strip_prefix("hugetlb.")
+            .and_then(|n| n.strip_suffix(".usage_in_bytes"))
+        {
+            // 跳过hugetlb.<size>.rsvd.usage_in_bytes
+            Some(size) if !size.contains('.') => size.to_owned(),
+            _ => continue,
+        };
+        let value = |file: &str| {
+            stats::parse_single_value(&path.join(format!("hugetlb.{}.{}", size, file)))
+        };
+        hugetlb.insert(
+            size.clone(),
+            HugeTlbStats {
+                usage: value("usage_in_bytes")?,
+                max: value("max_usage_in_bytes")?,
+                failcnt: value("failcnt")?,
+            },
+        );
+    }
+    Ok(hugetlb)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    fn test_blkio_entries() {
+        let entries = blkio_entries("8:0 Read 4096\n8:0 Write 0\nTotal 4096\n").unwrap();
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].op, "Read");
+        assert_eq!(entries[0].value, 4096);
+    }
+}
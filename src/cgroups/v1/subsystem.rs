@@ -1,4 +1,7 @@
+use crate::cgroups::common::ControllerOpt;
+use anyhow::Result;
 use std::fmt::Display;
+use std::path::Path;
 
 // root@vm:~# lssubsys -a
 // cpuset
@@ -41,15 +44,15 @@ impl Display for SubSystemType {
             Self::CpuAcct => "cpuacct",
             Self::Blkio => "blkio",
             Self::Memory => "memory",
-            Self::Devices => "pids",
-            Self::Freezer => "perf_event",
-            Self::NetCls => "memory",
-            Self::PerfEvent => "blkio",
-            Self::NetPrio => "freezer",
-            Self::HugeTlb => "freezer",
-            Self::Pids => "freezer",
-            Self::Rdma => "freezer",
-            Self::Misc => "freezer",
+            Self::Devices => "devices",
+            Self::Freezer => "freezer",
+            Self::NetCls => "net_cls",
+            Self::PerfEvent => "perf_event",
+            Self::NetPrio => "net_prio",
+            Self::HugeTlb => "hugetlb",
+            Self::Pids => "pids",
+            Self::Rdma => "rdma",
+            Self::Misc => "misc",
         };
         write!(f, "{}", print)
     }
@@ -73,6 +76,6 @@ pub const SUBSYSTEMLIST: &[SubSystemType] = &[
     SubSystemType::Misc,
 ];
 
-trait SubSystem {
-    fn add_task() {}
+pub trait SubSystem {
+    fn apply(controller_opt: &ControllerOpt, cgroup_path: &Path) -> Result<()>;
 }
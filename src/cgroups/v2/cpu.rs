@@ -14,7 +14,7 @@ pub struct Cpu {}
 impl SubSystem for Cpu {
     fn apply(controller_opt: &ControllerOpt, cgroup_path: &Path) -> Result<()> {
         if let Some(cpu) = &controller_opt.resources.cpu {
-            Self::apply(&cpu, cgroup_path)?;
+            Self::apply(cpu, cgroup_path)?;
         }
         Ok(())
     }
@@ -28,6 +28,10 @@ impl Cpu {
                 common::write_cgroup_file(path.join(CGROUP_CPU_WEIGHT), shares)?;
             }
         }
+        // 没有设置quota和period时不修改cpu.max, 避免覆盖systemd等其他方式设置的配额
+        if cpu.quota.is_none() && cpu.period.is_none() {
+            return Ok(());
+        }
         let mut quota_string = UNRESTRICTED_QUOTA.to_owned();
         if let Some(quota) = cpu.quota {
             if quota > 0 {
@@ -47,10 +51,26 @@ impl Cpu {
         Ok(())
     }
 
-    fn convert_shares_to_cgroup2(shares: u64) -> u64 {
+    //v1的shares范围是2-262144, 超出范围的值先截断再换算, 避免减法溢出
+    pub(crate) fn convert_shares_to_cgroup2(shares: u64) -> u64 {
         if shares == 0 {
             return 0;
         }
+        let shares = shares.clamp(2, 262144);
         1 + ((shares - 2) * 9999) / 262142
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    fn test_convert_shares_to_cgroup2() {
+        assert_eq!(Cpu::convert_shares_to_cgroup2(0), 0);
+        assert_eq!(Cpu::convert_shares_to_cgroup2(1), 1);
+        assert_eq!(Cpu::convert_shares_to_cgroup2(2), 1);
+        assert_eq!(Cpu::convert_shares_to_cgroup2(1024), 39);
+        assert_eq!(Cpu::convert_shares_to_cgroup2(262144), 10000);
+        assert_eq!(Cpu::convert_shares_to_cgroup2(u64::MAX), 10000);
+    }
+}
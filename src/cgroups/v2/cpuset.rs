@@ -0,0 +1,38 @@
+use super::subsystem::SubSystem;
+use crate::cgroups::common::{self, ControllerOpt};
+use anyhow::{Context, Result};
+use std::fs;
+use std::path::Path;
+
+const CGROUP_CPUSET_CPUS: &str = "cpuset.cpus";
+const CGROUP_CPUSET_MEMS: &str = "cpuset.mems";
+
+pub struct CpuSet {}
+
+impl SubSystem for CpuSet {
+    fn apply(controller_opt: &ControllerOpt, cgroup_path: &Path) -> Result<()> {
+        if let Some(cpu) = &controller_opt.resources.cpu {
+            if let Some(cpus) = &cpu.cpus {
+                Self::apply(cpus, CGROUP_CPUSET_CPUS, cgroup_path)?;
+            }
+            if let Some(mems) = &cpu.mems {
+                Self::apply(mems, CGROUP_CPUSET_MEMS, cgroup_path)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl CpuSet {
+    //v2中父cgroup实际可用的范围在cpuset.cpus.effective和cpuset.mems.effective中
+    fn apply(value: &str, file: &str, path: &Path) -> Result<()> {
+        if let Some(parent) = path.parent() {
+            let effective = parent.join(format!("{}.effective", file));
+            let available = fs::read_to_string(&effective)
+                .with_context(|| format!("failed to read {:?}", effective))?;
+            common::check_cpuset(value, &available)
+                .with_context(|| format!("invalid {} {:?}", file, value))?;
+        }
+        common::write_cgroup_file_str(path.join(file), value)
+    }
+}
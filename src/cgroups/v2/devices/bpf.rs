@@ -0,0 +1,137 @@
+use super::program::Insn;
+use anyhow::{Context, Result};
+use nix::errno::Errno;
+use std::mem::size_of;
+use std::os::unix::io::RawFd;
+
+// include/uapi/linux/bpf.h
+const BPF_PROG_LOAD: libc::c_long = 5;
+const BPF_PROG_ATTACH: libc::c_long = 8;
+const BPF_PROG_DETACH: libc::c_long = 9;
+const BPF_PROG_GET_FD_BY_ID: libc::c_long = 13;
+const BPF_PROG_QUERY: libc::c_long = 16;
+const BPF_PROG_TYPE_CGROUP_DEVICE: u32 = 15;
+const BPF_CGROUP_DEVICE: u32 = 6;
+const BPF_F_ALLOW_MULTI: u32 = 2;
+const LICENSE: &[u8] = b"Apache\0";
+const LOG_SIZE: usize = 64 * 1024;
+
+//bpf_attr是各个命令参数的union, 这里只定义用到的字段, 其余字段由内核按0处理
+#[repr(C)]
+#[derive(Default)]
+struct ProgLoadAttr {
+    prog_type: u32,
+    insn_cnt: u32,
+    insns: u64,
+    license: u64,
+    log_level: u32,
+    log_size: u32,
+    log_buf: u64,
+}
+
+#[repr(C)]
+#[derive(Default)]
+struct ProgAttachAttr {
+    target_fd: u32,
+    attach_bpf_fd: u32,
+    attach_type: u32,
+    attach_flags: u32,
+}
+
+#[repr(C)]
+#[derive(Default)]
+struct ProgQueryAttr {
+    target_fd: u32,
+    attach_type: u32,
+    query_flags: u32,
+    attach_flags: u32,
+    prog_ids: u64,
+    prog_cnt: u32,
+}
+
+#[repr(C)]
+#[derive(Default)]
+struct GetFdByIdAttr {
+    prog_id: u32,
+    next_id: u32,
+    open_flags: u32,
+}
+
+fn bpf<T>(cmd: libc::c_long, attr: &mut T) -> nix::Result<libc::c_long> {
+    let ret = unsafe { libc::syscall(libc::SYS_bpf, cmd, attr as *mut T, size_of::<T>()) };
+    Errno::result(ret)
+}
+
+//加载失败时带上verifier的日志重试一次, 方便定位问题
+pub fn prog_load(insns: &[Insn]) -> Result<RawFd> {
+    let mut attr = ProgLoadAttr {
+        prog_type: BPF_PROG_TYPE_CGROUP_DEVICE,
+        insn_cnt: insns.len() as u32,
+        insns: insns.as_ptr() as u64,
+        license: LICENSE.as_ptr() as u64,
+        ..Default::default()
+    };
+    if let Ok(fd) = bpf(BPF_PROG_LOAD, &mut attr) {
+        return Ok(fd as RawFd);
+    }
+    let mut log = vec![0u8; LOG_SIZE];
+    attr.log_level = 1;
+    attr.log_size = log.len() as u32;
+    attr.log_buf = log.as_mut_ptr() as u64;
+    let fd = bpf(BPF_PROG_LOAD, &mut attr).with_context(|| {
+        let len = log.iter().position(|b| *b == 0).unwrap_or(log.len());
+        format!(
+            "failed to load bpf program: {}",
+            String::from_utf8_lossy(&log[..len]).trim()
+        )
+    })?;
+    Ok(fd as RawFd)
+}
+
+//cgroup上已经attach的BPF_CGROUP_DEVICE程序id
+pub fn prog_query(cgroup_fd: RawFd) -> Result<Vec<u32>> {
+    let mut ids = vec![0u32; 64];
+    let mut attr = ProgQueryAttr {
+        target_fd: cgroup_fd as u32,
+        attach_type: BPF_CGROUP_DEVICE,
+        prog_ids: ids.as_mut_ptr() as u64,
+        prog_cnt: ids.len() as u32,
+        ..Default::default()
+    };
+    bpf(BPF_PROG_QUERY, &mut attr).context("failed to query bpf programs")?;
+    ids.truncate(attr.prog_cnt as usize);
+    Ok(ids)
+}
+
+pub fn prog_get_fd_by_id(id: u32) -> Result<RawFd> {
+    let mut attr = GetFdByIdAttr {
+        prog_id: id,
+        ..Default::default()
+    };
+    let fd = bpf(BPF_PROG_GET_FD_BY_ID, &mut attr)
+        .with_context(|| format!("failed to get bpf program {}", id))?;
+    Ok(fd as RawFd)
+}
+
+//使用BPF_F_ALLOW_MULTI, 替换程序时可以先attach新的再detach旧的
+pub fn prog_attach(prog_fd: RawFd, cgroup_fd: RawFd) -> Result<()> {
+    let mut attr = ProgAttachAttr {
+        target_fd: cgroup_fd as u32,
+        attach_bpf_fd: prog_fd as u32,
+        attach_type: BPF_CGROUP_DEVICE,
+        attach_flags: BPF_F_ALLOW_MULTI,
+    };
+    bpf(BPF_PROG_ATTACH, &mut attr).context("failed to attach bpf program")?;
+    Ok(())
+}
+
+pub fn prog_detach(prog_fd: RawFd, cgroup_fd: RawFd) -> Result<()> {
+    let mut attr = ProgAttachAttr {
+        target_fd: cgroup_fd as u32,
+        attach_bpf_fd: prog_fd as u32,
+        attach_type: BPF_CGROUP_DEVICE,
+        ..Default::default()
+    };
+    bpf(BPF_PROG_DETACH, &mut attr).context("failed to detach bpf program")?;
+    Ok(())
+}
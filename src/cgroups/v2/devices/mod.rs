@@ -0,0 +1,55 @@
+mod bpf;
+mod program;
+
+use super::subsystem::SubSystem;
+use crate::cgroups::common::{self, ControllerOpt};
+use crate::oci::oci::LinuxDeviceCgroup;
+use anyhow::{Context, Result};
+use nix::fcntl::{open, OFlag};
+use nix::sys::stat::Mode;
+use nix::unistd::close;
+use std::os::unix::io::RawFd;
+use std::path::Path;
+
+//v2中没有devices controller, 需要把规则编译为BPF_PROG_TYPE_CGROUP_DEVICE程序attach到cgroup上
+pub struct Devices {}
+
+impl SubSystem for Devices {
+    fn apply(controller_opt: &ControllerOpt, cgroup_path: &Path) -> Result<()> {
+        if let Some(devices) = &controller_opt.resources.devices {
+            let mut rules = devices.clone();
+            rules.extend(common::default_allowed_devices());
+            Self::apply(&rules, cgroup_path)?;
+        }
+        Ok(())
+    }
+}
+
+impl Devices {
+    fn apply(rules: &[LinuxDeviceCgroup], path: &Path) -> Result<()> {
+        let insns = program::build(rules)?;
+        let prog_fd = bpf::prog_load(&insns)?;
+        let ret = open(path, OFlag::O_RDONLY | OFlag::O_DIRECTORY, Mode::empty())
+            .with_context(|| format!("failed to open {:?}", path))
+            .and_then(|cgroup_fd| {
+                let ret = Self::replace(prog_fd, cgroup_fd);
+                let _ = close(cgroup_fd);
+                ret
+            });
+        let _ = close(prog_fd);
+        ret
+    }
+
+    //update时cgroup上已经有之前的程序, 需要在新程序attach之后detach
+    fn replace(prog_fd: RawFd, cgroup_fd: RawFd) -> Result<()> {
+        let old = bpf::prog_query(cgroup_fd)?;
+        bpf::prog_attach(prog_fd, cgroup_fd)?;
+        for id in old {
+            let old_fd = bpf::prog_get_fd_by_id(id)?;
+            let ret = bpf::prog_detach(old_fd, cgroup_fd);
+            let _ = close(old_fd);
+            ret?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,174 @@
+use crate::oci::oci::LinuxDeviceCgroup;
+use anyhow::{bail, Result};
+
+// struct bpf_cgroup_dev_ctx { u32 access_type; u32 major; u32 minor; }
+// access_type的低16位是设备类型, 高16位是访问类型
+const BPF_DEVCG_DEV_BLOCK: i32 = 1;
+const BPF_DEVCG_DEV_CHAR: i32 = 2;
+const BPF_DEVCG_ACC_MKNOD: i32 = 1;
+const BPF_DEVCG_ACC_READ: i32 = 2;
+const BPF_DEVCG_ACC_WRITE: i32 = 4;
+const ACC_ALL: i32 = BPF_DEVCG_ACC_MKNOD | BPF_DEVCG_ACC_READ | BPF_DEVCG_ACC_WRITE;
+
+const BPF_LDX_MEM_W: u8 = 0x61;
+const BPF_ALU_AND_K: u8 = 0x54;
+const BPF_ALU_RSH_K: u8 = 0x74;
+const BPF_ALU64_MOV_K: u8 = 0xb7;
+const BPF_ALU64_MOV_X: u8 = 0xbf;
+const BPF_JMP_JEQ_K: u8 = 0x15;
+const BPF_JMP_JNE_K: u8 = 0x55;
+const BPF_JMP_JNE_X: u8 = 0x5d;
+const BPF_JMP_EXIT: u8 = 0x95;
+
+const R0: u8 = 0;
+const R1: u8 = 1;
+// 设备类型
+const R2: u8 = 2;
+// 访问类型
+const R3: u8 = 3;
+const R4: u8 = 4;
+const R5: u8 = 5;
+
+// struct bpf_insn
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Insn {
+    code: u8,
+    regs: u8,
+    off: i16,
+    imm: i32,
+}
+
+impl Insn {
+    fn new(code: u8, dst: u8, src: u8, off: i16, imm: i32) -> Insn {
+        Insn {
+            code,
+            regs: dst | src << 4,
+            off,
+            imm,
+        }
+    }
+}
+
+//生成检查设备访问的程序, 返回1表示允许, 0表示拒绝
+//规则按顺序生效, 后面的覆盖前面的, 所以从最后一条规则开始匹配
+pub fn build(rules: &[LinuxDeviceCgroup]) -> Result<Vec<Insn>> {
+    let mut insns = vec![
+        Insn::new(BPF_LDX_MEM_W, R2, R1, 0, 0),
+        Insn::new(BPF_ALU64_MOV_X, R3, R2, 0, 0),
+        Insn::new(BPF_ALU_AND_K, R2, 0, 0, 0xffff),
+        Insn::new(BPF_ALU_RSH_K, R3, 0, 0, 16),
+        Insn::new(BPF_LDX_MEM_W, R4, R1, 4, 0),
+        Insn::new(BPF_LDX_MEM_W, R5, R1, 8, 0),
+    ];
+    for rule in rules.iter().rev() {
+        let (block, matches_all) = rule_block(rule)?;
+        insns.extend(block);
+        // 之后的指令都不可达, verifier会拒绝有不可达指令的程序
+        if matches_all {
+            return Ok(insns);
+        }
+    }
+    // 没有匹配的规则时允许, 与v1中新建cgroup的默认行为一致
+    insns.push(Insn::new(BPF_ALU64_MOV_K, R0, 0, 0, 1));
+    insns.push(Insn::new(BPF_JMP_EXIT, 0, 0, 0, 0));
+    Ok(insns)
+}
+
+//匹配时直接返回结果, 不匹配时跳到下一条规则, 同时返回规则是否匹配所有设备
+fn rule_block(rule: &LinuxDeviceCgroup) -> Result<(Vec<Insn>, bool)> {
+    let typ = match rule.typ.as_deref().unwrap_or("a") {
+        "a" => None,
+        "b" => Some(BPF_DEVCG_DEV_BLOCK),
+        "c" => Some(BPF_DEVCG_DEV_CHAR),
+        typ => bail!("invalid device type {:?}", typ),
+    };
+    let access = parse_access(rule.access.as_deref().unwrap_or("rwm"))?;
+    let mut block = Vec::new();
+    let mut jumps = Vec::new();
+    if let Some(typ) = typ {
+        jumps.push(block.len());
+        block.push(Insn::new(BPF_JMP_JNE_K, R2, 0, 0, typ));
+    }
+    if access != ACC_ALL {
+        block.push(Insn::new(BPF_ALU64_MOV_X, R1, R3, 0, 0));
+        block.push(Insn::new(BPF_ALU_AND_K, R1, 0, 0, access));
+        jumps.push(block.len());
+        if rule.allow {
+            // 请求的访问类型需要全部被允许
+            block.push(Insn::new(BPF_JMP_JNE_X, R1, R3, 0, 0));
+        } else {
+            // 请求中有任意一种被禁止的访问类型就拒绝
+            block.push(Insn::new(BPF_JMP_JEQ_K, R1, 0, 0, 0));
+        }
+    }
+    for (reg, number) in [(R4, rule.major), (R5, rule.minor)] {
+        // 没有指定或者为-1时匹配所有
+        if let Some(number) = number.filter(|n| *n >= 0) {
+            jumps.push(block.len());
+            block.push(Insn::new(BPF_JMP_JNE_K, reg, 0, 0, number as i32));
+        }
+    }
+    block.push(Insn::new(BPF_ALU64_MOV_K, R0, 0, 0, rule.allow as i32));
+    block.push(Insn::new(BPF_JMP_EXIT, 0, 0, 0, 0));
+    let len = block.len();
+    let matches_all = jumps.is_empty();
+    for i in jumps {
+        block[i].off = (len - i - 1) as i16;
+    }
+    Ok((block, matches_all))
+}
+
+fn parse_access(access: &str) -> Result<i32> {
+    let mut bits = 0;
+    for c in access.chars() {
+        bits |= match c {
+            'r' => BPF_DEVCG_ACC_READ,
+            'w' => BPF_DEVCG_ACC_WRITE,
+            'm' => BPF_DEVCG_ACC_MKNOD,
+            _ => bail!("invalid device access {:?}", access),
+        };
+    }
+    Ok(bits)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    fn test_rule_block() {
+        let rule = LinuxDeviceCgroup {
+            allow: true,
+            typ: Some("c".to_owned()),
+            major: Some(1),
+            minor: Some(3),
+            access: Some("rw".to_owned()),
+        };
+        let (block, matches_all) = rule_block(&rule).unwrap();
+        assert_eq!(block.len(), 8);
+        assert!(!matches_all);
+        // 每个跳转都落在下一条规则的开头
+        for (i, off) in [(0, 7), (3, 4), (4, 3), (5, 2)] {
+            assert_eq!(block[i].off, off);
+        }
+        assert_eq!(block[6], Insn::new(BPF_ALU64_MOV_K, R0, 0, 0, 1));
+
+        let deny_all = LinuxDeviceCgroup {
+            allow: false,
+            access: Some("rwm".to_owned()),
+            ..Default::default()
+        };
+        let (block, matches_all) = rule_block(&deny_all).unwrap();
+        assert_eq!(block.len(), 2);
+        assert!(matches_all);
+        // deny all之前的规则不会生效
+        let program = build(&[rule, deny_all]).unwrap();
+        assert_eq!(program.len(), 8);
+
+        let invalid = LinuxDeviceCgroup {
+            access: Some("rx".to_owned()),
+            ..Default::default()
+        };
+        assert!(rule_block(&invalid).is_err());
+    }
+}
@@ -0,0 +1,60 @@
+use crate::cgroups::common;
+use crate::cgroups::FreezerState;
+use anyhow::{bail, Context, Result};
+use nix::poll::{poll, PollFd, PollFlags};
+use nix::sys::inotify::{AddWatchFlags, InitFlags, Inotify};
+use nix::unistd::close;
+use std::fs;
+use std::os::unix::io::AsRawFd;
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+const CGROUP_FREEZE: &str = "cgroup.freeze";
+const CGROUP_EVENTS: &str = "cgroup.events";
+const FREEZE_TIMEOUT: Duration = Duration::from_secs(10);
+
+pub struct Freezer {}
+
+impl Freezer {
+    //写入cgroup.freeze后内核异步冻结进程, 完成时cgroup.events中的frozen字段会改变
+    pub fn apply(state: FreezerState, path: &Path) -> Result<()> {
+        let (value, frozen) = match state {
+            FreezerState::Frozen => ("1", true),
+            FreezerState::Thawed => ("0", false),
+        };
+        let events_path = path.join(CGROUP_EVENTS);
+        // 先监听再写入, 避免错过修改事件
+        let inotify = Inotify::init(InitFlags::IN_NONBLOCK | InitFlags::IN_CLOEXEC)?;
+        let result = inotify
+            .add_watch(&events_path, AddWatchFlags::IN_MODIFY)
+            .map_err(anyhow::Error::from)
+            .and_then(|_| common::write_cgroup_file_str(path.join(CGROUP_FREEZE), value))
+            .and_then(|_| Self::wait(inotify, &events_path, frozen));
+        // nix的Inotify不会在drop时关闭fd, 需要手动关闭
+        let _ = close(inotify.as_raw_fd());
+        result.with_context(|| format!("failed to set cgroup {:?} to {:?}", path, state))
+    }
+
+    fn wait(inotify: Inotify, events_path: &Path, frozen: bool) -> Result<()> {
+        let deadline = Instant::now() + FREEZE_TIMEOUT;
+        loop {
+            if Self::is_frozen(events_path)? == frozen {
+                return Ok(());
+            }
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            if remaining.is_zero() {
+                bail!("timed out waiting for {:?}", events_path);
+            }
+            let mut fds = [PollFd::new(inotify.as_raw_fd(), PollFlags::POLLIN)];
+            poll(&mut fds, remaining.as_millis() as i32)?;
+            // 只需要清空事件, 状态以文件内容为准
+            let _ = inotify.read_events();
+        }
+    }
+
+    fn is_frozen(events_path: &Path) -> Result<bool> {
+        let events = fs::read_to_string(events_path)
+            .with_context(|| format!("failed to read {:?}", events_path))?;
+        Ok(events.lines().any(|line| line.trim() == "frozen 1"))
+    }
+}
@@ -0,0 +1,29 @@
+use super::subsystem::SubSystem;
+use crate::cgroups::common::{self, ControllerOpt};
+use crate::oci::oci::LinuxHugepageLimit;
+use anyhow::{bail, Result};
+use std::path::Path;
+
+pub struct HugeTlb {}
+
+impl SubSystem for HugeTlb {
+    fn apply(controller_opt: &ControllerOpt, cgroup_path: &Path) -> Result<()> {
+        if let Some(limits) = &controller_opt.resources.hugepage_limits {
+            for limit in limits {
+                Self::apply(limit, cgroup_path)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl HugeTlb {
+    //每种内核支持的大页大小都有对应的hugetlb.<size>.max
+    fn apply(limit: &LinuxHugepageLimit, path: &Path) -> Result<()> {
+        let file = path.join(format!("hugetlb.{}.max", limit.page_size));
+        if !file.exists() {
+            bail!("hugepage size {} is not supported", limit.page_size);
+        }
+        common::write_cgroup_file(file, limit.limit)
+    }
+}
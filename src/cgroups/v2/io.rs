@@ -0,0 +1,94 @@
+use super::subsystem::SubSystem;
+use crate::cgroups::common::{self, ControllerOpt};
+use crate::oci::oci::LinuxBlockIo;
+use anyhow::{bail, Result};
+use std::path::Path;
+
+const CGROUP_IO_WEIGHT: &str = "io.weight";
+const CGROUP_BFQ_IO_WEIGHT: &str = "io.bfq.weight";
+const CGROUP_IO_MAX: &str = "io.max";
+
+pub struct Io {}
+
+impl SubSystem for Io {
+    fn apply(controller_opt: &ControllerOpt, cgroup_path: &Path) -> Result<()> {
+        if let Some(block_io) = &controller_opt.resources.block_io {
+            Self::apply(block_io, cgroup_path)?;
+        }
+        Ok(())
+    }
+}
+
+impl Io {
+    fn apply(block_io: &LinuxBlockIo, path: &Path) -> Result<()> {
+        let weight_devices = block_io.weight_device.as_deref().unwrap_or_default();
+        if block_io.leaf_weight.is_some() || weight_devices.iter().any(|d| d.leaf_weight.is_some())
+        {
+            bail!("blkio leaf weight is not supported in cgroup v2");
+        }
+        if let Some(weight) = block_io.weight {
+            Self::set_weight(path, None, weight)?;
+        }
+        for device in weight_devices {
+            if let Some(weight) = device.weight {
+                Self::set_weight(path, Some(&common::device_number(&device.device)?), weight)?;
+            }
+        }
+        // io.max中每行对应一个设备, 只写入的key会被修改
+        for (devices, key) in [
+            (&block_io.throttle_read_bps_device, "rbps"),
+            (&block_io.throttle_write_bps_device, "wbps"),
+            (&block_io.throttle_read_iops_device, "riops"),
+            (&block_io.throttle_write_iops_device, "wiops"),
+        ] {
+            for device in devices.iter().flatten() {
+                // 0表示不限制
+                let rate = match device.rate {
+                    0 => "max".to_owned(),
+                    rate => rate.to_string(),
+                };
+                let line = format!(
+                    "{} {}={}",
+                    common::device_number(&device.device)?,
+                    key,
+                    rate
+                );
+                common::write_cgroup_file_str(path.join(CGROUP_IO_MAX), &line)?;
+            }
+        }
+        Ok(())
+    }
+
+    //bfq调度器的权重范围与v1的blkio.weight相同, 可以直接写入, 否则需要换算到io.weight的范围
+    fn set_weight(path: &Path, device: Option<&str>, weight: u16) -> Result<()> {
+        if !(10..=1000).contains(&weight) {
+            bail!("invalid blkio weight {}, must be in 10-1000", weight);
+        }
+        let (file, weight) = if path.join(CGROUP_BFQ_IO_WEIGHT).exists() {
+            (CGROUP_BFQ_IO_WEIGHT, weight as u64)
+        } else {
+            (CGROUP_IO_WEIGHT, Self::convert_weight_to_cgroup2(weight))
+        };
+        let value = match device {
+            Some(device) => format!("{} {}", device, weight),
+            None => weight.to_string(),
+        };
+        common::write_cgroup_file_str(path.join(file), &value)
+    }
+
+    //blkio.weight的范围是10-1000, io.weight是1-10000
+    pub(crate) fn convert_weight_to_cgroup2(weight: u16) -> u64 {
+        1 + (weight as u64 - 10) * 9999 / 990
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    fn test_convert_weight_to_cgroup2() {
+        assert_eq!(Io::convert_weight_to_cgroup2(10), 1);
+        assert_eq!(Io::convert_weight_to_cgroup2(500), 4950);
+        assert_eq!(Io::convert_weight_to_cgroup2(1000), 10000);
+    }
+}
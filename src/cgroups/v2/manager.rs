@@ -1,10 +1,19 @@
 use super::cpu::Cpu;
+use super::cpuset::CpuSet;
+use super::devices::Devices;
+use super::freezer::Freezer;
+use super::hugetlb::HugeTlb;
+use super::io::Io;
+use super::memory::Memory;
+use super::oom::MemoryEventsNotifier;
+use super::pids::Pids;
+use super::stats;
 use super::subsystem::{SubSystem, SubSystemType, SUBSYSTEMLIST};
-use crate::cgroups::common;
 use crate::cgroups::common::ControllerOpt;
-use crate::cgroups::CgroupManager;
-use crate::cgroups::SMOG;
-use anyhow::Result;
+use crate::cgroups::common::{self, CGROUP_PROCS};
+use crate::cgroups::stats::Stats;
+use crate::cgroups::{CgroupManager, FreezerState, OomNotifier};
+use anyhow::{Context, Result};
 use nix::unistd::Pid;
 use std::fs;
 use std::os::unix::fs::PermissionsExt;
@@ -13,7 +22,6 @@ use std::path::{Path, PathBuf};
 
 pub const CGROUP_CONTROLLERS: &str = "cgroup.controllers";
 pub const CGROUP_SUBTREE_CONTROL: &str = "cgroup.subtree_control";
-pub const CGROUP_PROCS: &str = "cgroup.procs";
 
 pub struct Manager {
     root_path: PathBuf,
@@ -22,46 +30,49 @@ pub struct Manager {
 }
 
 impl Manager {
-    pub fn new(root_path: PathBuf, container_id: &str) -> Manager {
-        let cgroups_path = PathBuf::from(format!("{}/{}", SMOG, container_id));
-        let full_path = root_path.join(&cgroups_path);
+    pub fn new(root_path: PathBuf, cgroups_path: &Path) -> Manager {
+        let full_path = root_path.join(cgroups_path);
         Self {
-            root_path: root_path,
-            cgroups_path: cgroups_path,
-            full_path: full_path,
+            root_path,
+            cgroups_path: cgroups_path.to_path_buf(),
+            full_path,
         }
     }
 
+    //逐级创建cgroup, 每一级都在父cgroup中启用子cgroup需要的controller
     fn create_unified_cgroup(&self, pid: Pid) -> Result<()> {
-        let controllers: Vec<String> = SUBSYSTEMLIST
-            .iter()
-            .map(|c| format!("{}{}", "+", c.to_string()))
-            .collect();
-        let mut components = self
-            .cgroups_path
-            .components()
-            .filter(|c| c.ne(&RootDir))
-            .peekable();
         let mut current_path = self.root_path.clone();
-        while let Some(component) = components.next() {
+        for component in self.cgroups_path.components().filter(|c| c.ne(&RootDir)) {
+            Self::enable_controllers(&current_path)?;
             current_path = current_path.join(component);
-            println!("component:{:?} , {:?}", component, current_path);
             if !current_path.exists() {
                 fs::create_dir(&current_path)?;
                 fs::metadata(&current_path)?.permissions().set_mode(0o755);
             }
-            // (device or resource busy)
-            if components.peek().is_some() {
-                Self::write_controllers(&current_path, &controllers)?;
-            }
         }
-        common::write_cgroup_file(&self.full_path.join(CGROUP_PROCS), pid)?;
+        common::write_cgroup_file(self.full_path.join(CGROUP_PROCS), pid)?;
         Ok(())
     }
 
-    fn write_controllers(path: &Path, controllers: &[String]) -> Result<()> {
-        for controller in controllers {
-            common::write_cgroup_file_str(path.join(CGROUP_SUBTREE_CONTROL), controller)?;
+    //只启用父cgroup中可用的controller, 已经启用的不再写入,
+    //这样在委托给当前用户的cgroup下创建时不需要上层cgroup的写权限
+    fn enable_controllers(path: &Path) -> Result<()> {
+        let read = |file: &str| {
+            fs::read_to_string(path.join(file))
+                .with_context(|| format!("failed to read {:?}", path.join(file)))
+        };
+        let available = read(CGROUP_CONTROLLERS)?;
+        let enabled = read(CGROUP_SUBTREE_CONTROL)?;
+        for controller in SUBSYSTEMLIST.iter().map(|c| c.to_string()) {
+            if available.split_whitespace().any(|c| c == controller)
+                && !enabled.split_whitespace().any(|c| c == controller)
+            {
+                common::write_cgroup_file_str(
+                    path.join(CGROUP_SUBTREE_CONTROL),
+                    &format!("+{}", controller),
+                )
+                .with_context(|| format!("failed to enable {} in {:?}", controller, path))?;
+            }
         }
         Ok(())
     }
@@ -77,9 +88,34 @@ impl CgroupManager for Manager {
         for controller in SUBSYSTEMLIST {
             match controller {
                 SubSystemType::Cpu => Cpu::apply(controller_opt, &self.full_path)?,
-                _ => {}
+                SubSystemType::CpuSet => CpuSet::apply(controller_opt, &self.full_path)?,
+                SubSystemType::Io => Io::apply(controller_opt, &self.full_path)?,
+                SubSystemType::Memory => Memory::apply(controller_opt, &self.full_path)?,
+                SubSystemType::HugeTlb => HugeTlb::apply(controller_opt, &self.full_path)?,
+                SubSystemType::Pids => Pids::apply(controller_opt, &self.full_path)?,
             }
         }
+        Devices::apply(controller_opt, &self.full_path)?;
         Ok(())
     }
+
+    fn get_all_pids(&self) -> Result<Vec<Pid>> {
+        common::get_all_pids(&self.full_path)
+    }
+
+    fn freeze(&self, state: FreezerState) -> Result<()> {
+        Freezer::apply(state, &self.full_path)
+    }
+
+    fn stats(&self) -> Result<Stats> {
+        stats::stats(&self.full_path)
+    }
+
+    fn oom_notifier(&self) -> Result<Box<dyn OomNotifier>> {
+        Ok(Box::new(MemoryEventsNotifier::new(&self.full_path)?))
+    }
+
+    fn remove(&self) -> Result<()> {
+        common::remove_cgroup(&self.root_path, &self.cgroups_path)
+    }
 }
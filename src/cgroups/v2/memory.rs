@@ -1 +1,127 @@
-struct Memory {}
+use super::subsystem::SubSystem;
+use crate::cgroups::common::{self, ControllerOpt};
+use crate::oci::oci::LinuxMemory;
+use anyhow::{bail, Result};
+use std::path::Path;
+
+const CGROUP_MEMORY_MAX: &str = "memory.max";
+const CGROUP_MEMORY_LOW: &str = "memory.low";
+const CGROUP_MEMORY_SWAP_MAX: &str = "memory.swap.max";
+const UNLIMITED: &str = "max";
+
+pub struct Memory {}
+
+impl SubSystem for Memory {
+    fn apply(controller_opt: &ControllerOpt, cgroup_path: &Path) -> Result<()> {
+        if let Some(memory) = &controller_opt.resources.memory {
+            Self::apply(memory, cgroup_path)?;
+        }
+        Ok(())
+    }
+}
+
+impl Memory {
+    fn apply(memory: &LinuxMemory, path: &Path) -> Result<()> {
+        // 不限制(-1)的kernel memory与v2的语义一致, 可以忽略
+        if let Some(kernel) = memory.kernel.filter(|k| *k != -1) {
+            bail!(
+                "kernel memory limit {} is not supported in cgroup v2",
+                kernel
+            );
+        }
+        if let Some(kernel_tcp) = memory.kernel_tcp.filter(|k| *k != -1) {
+            bail!(
+                "kernel tcp memory limit {} is not supported in cgroup v2",
+                kernel_tcp
+            );
+        }
+        if let Some(swappiness) = memory.swappiness {
+            if swappiness != 0 {
+                bail!(
+                    "memory swappiness {} is not supported in cgroup v2",
+                    swappiness
+                );
+            }
+        }
+        if let Some(limit) = memory.limit {
+            Self::write_limit(path, CGROUP_MEMORY_MAX, limit)?;
+        }
+        if let Some(reservation) = memory.reservation {
+            Self::write_limit(path, CGROUP_MEMORY_LOW, reservation)?;
+        }
+        if let Some(swap) = Self::swap_max(memory)? {
+            Self::write_limit(path, CGROUP_MEMORY_SWAP_MAX, swap)?;
+        }
+        Ok(())
+    }
+
+    //oci中的swap是memory+swap的总量, 而memory.swap.max只限制swap, 需要减去memory的限制
+    pub(crate) fn swap_max(memory: &LinuxMemory) -> Result<Option<i64>> {
+        let swap = match memory.swap {
+            Some(swap) if swap != 0 => swap,
+            _ => return Ok(None),
+        };
+        if swap == -1 {
+            return Ok(Some(-1));
+        }
+        let limit = match memory.limit {
+            Some(limit) if limit > 0 => limit,
+            _ => bail!("memory+swap limit {} is set without a memory limit", swap),
+        };
+        if swap < limit {
+            bail!(
+                "memory+swap limit {} should be larger than memory limit {}",
+                swap,
+                limit
+            );
+        }
+        Ok(Some(swap - limit))
+    }
+
+    // -1表示不限制
+    fn write_limit(path: &Path, file: &str, value: i64) -> Result<()> {
+        match value {
+            -1 => common::write_cgroup_file_str(path.join(file), UNLIMITED),
+            v if v < 0 => bail!("invalid value {} for {}", v, file),
+            v => common::write_cgroup_file(path.join(file), v),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    fn test_swap_max() {
+        let mut memory = LinuxMemory {
+            limit: Some(1024),
+            swap: Some(3072),
+            ..Default::default()
+        };
+        assert_eq!(Memory::swap_max(&memory).unwrap(), Some(2048));
+
+        memory.swap = Some(-1);
+        assert_eq!(Memory::swap_max(&memory).unwrap(), Some(-1));
+
+        memory.swap = Some(512);
+        let err = Memory::swap_max(&memory).unwrap_err();
+        assert!(err.to_string().contains("should be larger"));
+
+        memory.limit = None;
+        assert!(Memory::swap_max(&memory).is_err());
+    }
+
+    #[test]
+    fn test_unsupported_settings() {
+        let mut memory = LinuxMemory {
+            kernel: Some(-1),
+            swappiness: Some(0),
+            ..Default::default()
+        };
+        assert!(Memory::apply(&memory, Path::new("/nonexistent")).is_ok());
+
+        memory.kernel = Some(1024);
+        let err = Memory::apply(&memory, Path::new("/nonexistent")).unwrap_err();
+        assert!(err.to_string().contains("not supported"));
+    }
+}
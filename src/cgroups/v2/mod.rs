@@ -1,3 +1,12 @@
-mod cpu;
+pub(crate) mod cpu;
+mod cpuset;
+mod devices;
+mod freezer;
+mod hugetlb;
+pub(crate) mod io;
 pub mod manager;
+pub(crate) mod memory;
+mod oom;
+mod pids;
+mod stats;
 mod subsystem;
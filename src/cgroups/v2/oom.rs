@@ -0,0 +1,62 @@
+use crate::cgroups::stats;
+use crate::cgroups::OomNotifier;
+use anyhow::Result;
+use nix::sys::inotify::{AddWatchFlags, InitFlags, Inotify};
+use nix::unistd::close;
+use std::os::unix::io::{AsRawFd, RawFd};
+use std::path::{Path, PathBuf};
+
+const MEMORY_EVENTS: &str = "memory.events";
+
+//memory.events发生变化时inotify可读, 通过oom_kill计数的增量判断是否发生了OOM
+pub struct MemoryEventsNotifier {
+    inotify: Inotify,
+    path: PathBuf,
+    oom_kill: u64,
+}
+
+impl MemoryEventsNotifier {
+    pub fn new(cgroup_path: &Path) -> Result<Self> {
+        let path = cgroup_path.join(MEMORY_EVENTS);
+        let inotify = Inotify::init(InitFlags::IN_NONBLOCK | InitFlags::IN_CLOEXEC)?;
+        let mut notifier = Self {
+            inotify,
+            path,
+            oom_kill: 0,
+        };
+        notifier
+            .inotify
+            .add_watch(&notifier.path, AddWatchFlags::IN_MODIFY)?;
+        notifier.oom_kill = notifier.read_oom_kill()?;
+        Ok(notifier)
+    }
+
+    fn read_oom_kill(&self) -> Result<u64> {
+        let events = stats::parse_flat_keyed(&self.path)?;
+        Ok(events.get("oom_kill").copied().unwrap_or_default())
+    }
+}
+
+impl OomNotifier for MemoryEventsNotifier {
+    fn as_raw_fd(&self) -> RawFd {
+        self.inotify.as_raw_fd()
+    }
+
+    fn read(&mut self) -> Result<u64> {
+        let _ = self.inotify.read_events();
+        // cgroup被删除后不会再有OOM事件
+        if !self.path.exists() {
+            return Ok(0);
+        }
+        let oom_kill = self.read_oom_kill()?;
+        let count = oom_kill.saturating_sub(self.oom_kill);
+        self.oom_kill = oom_kill;
+        Ok(count)
+    }
+}
+
+impl Drop for MemoryEventsNotifier {
+    fn drop(&mut self) {
+        let _ = close(self.inotify.as_raw_fd());
+    }
+}
@@ -0,0 +1,22 @@
+use super::subsystem::SubSystem;
+use crate::cgroups::common::{self, ControllerOpt};
+use anyhow::Result;
+use std::path::Path;
+
+const CGROUP_PIDS_MAX: &str = "pids.max";
+
+pub struct Pids {}
+
+impl SubSystem for Pids {
+    fn apply(controller_opt: &ControllerOpt, cgroup_path: &Path) -> Result<()> {
+        if let Some(pids) = &controller_opt.resources.pids {
+            // 小于等于0表示不限制
+            if pids.limit > 0 {
+                common::write_cgroup_file(cgroup_path.join(CGROUP_PIDS_MAX), pids.limit)?;
+            } else {
+                common::write_cgroup_file_str(cgroup_path.join(CGROUP_PIDS_MAX), "max")?;
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,154 @@
+use crate::cgroups::stats::{
+    self, BlkioEntry, BlkioStats, CpuStats, HugeTlbStats, MemoryEntry, MemoryStats, PidsStats,
+    Stats,
+};
+use anyhow::{Context, Result};
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+//没有启用的controller不存在对应的文件, 跳过即可
+pub fn stats(path: &Path) -> Result<Stats> {
+    let mut stats = Stats::default();
+    if path.join("cpu.stat").exists() {
+        stats.cpu = cpu_stats(path)?;
+    }
+    if path.join("memory.current").exists() {
+        stats.memory = memory_stats(path)?;
+    }
+    if path.join("pids.current").exists() {
+        stats.pids = PidsStats {
+            current: stats::parse_single_value(&path.join("pids.current"))?,
+            limit: stats::parse_single_value(&path.join("pids.max"))?,
+        };
+    }
+    if path.join("io.stat").exists() {
+        stats.blkio = io_stats(&fs::read_to_string(path.join("io.stat"))?)?;
+    }
+    stats.hugetlb = hugetlb_stats(path)?;
+    Ok(stats)
+}
+
+//cpu.stat中的时间单位是微秒
+fn cpu_stats(path: &Path) -> Result<CpuStats> {
+    let values = stats::parse_flat_keyed(&path.join("cpu.stat"))?;
+    let get = |key: &str| values.get(key).copied().unwrap_or_default();
+    let mut cpu = CpuStats::default();
+    cpu.usage.total = get("usage_usec") * 1000;
+    cpu.usage.user = get("user_usec") * 1000;
+    cpu.usage.kernel = get("system_usec") * 1000;
+    cpu.throttling.periods = get("nr_periods");
+    cpu.throttling.throttled_periods = get("nr_throttled");
+    cpu.throttling.throttled_time = get("throttled_usec") * 1000;
+    Ok(cpu)
+}
+
+fn memory_stats(path: &Path) -> Result<MemoryStats> {
+    let optional = |file: &str| -> Result<u64> {
+        let p = path.join(file);
+        if p.exists() {
+            stats::parse_single_value(&p)
+        } else {
+            Ok(0)
+        }
+    };
+    let events = stats::parse_flat_keyed(&path.join("memory.events"))?;
+    Ok(MemoryStats {
+        usage: MemoryEntry {
+            limit: stats::parse_single_value(&path.join("memory.max"))?,
+            usage: stats::parse_single_value(&path.join("memory.current"))?,
+            // memory.peak在较新的内核中才有
+            max: optional("memory.peak")?,
+            failcnt: events.get("max").copied().unwrap_or_default(),
+        },
+        swap: MemoryEntry {
+            limit: optional("memory.swap.max")?,
+            usage: optional("memory.swap.current")?,
+            max: optional("memory.swap.peak")?,
+            failcnt: 0,
+        },
+        raw: stats::parse_flat_keyed(&path.join("memory.stat"))?,
+    })
+}
+
+//io.stat每行一个设备: "8:0 rbytes=1 wbytes=2 rios=3 wios=4 dbytes=0 dios=0"
+fn io_stats(content: &str) -> Result<BlkioStats> {
+    let mut blkio = BlkioStats::default();
+    for line in content.lines() {
+        let mut fields = line.split_whitespace();
+        let (major, minor) = match fields.next() {
+            Some(device) => stats::parse_device_number(device)?,
+            None => continue,
+        };
+        for field in fields {
+            let (key, value) = match field.split_once('=') {
+                Some(kv) => kv,
+                None => continue,
+            };
+            let value: u64 = value
+                .parse()
+                .with_context(|| format!("invalid io.stat field {:?}", field))?;
+            let (op, entries) = match key {
+                "rbytes" => ("Read", &mut blkio.io_service_bytes_recursive),
+                "wbytes" => ("Write", &mut blkio.io_service_bytes_recursive),
+                "rios" => ("Read", &mut blkio.io_serviced_recursive),
+                "wios" => ("Write", &mut blkio.io_serviced_recursive),
+                _ => continue,
+            };
+            entries.push(BlkioEntry {
+                major,
+                minor,
+                op: op.to_owned(),
+                value,
+            });
+        }
+    }
+    Ok(blkio)
+}
+
+//每种大页大小对应一组hugetlb.<size>.*文件
+fn hugetlb_stats(path: &Path) -> Result<HashMap<String, HugeTlbStats>> {
+    let mut hugetlb = HashMap::new();
+    for entry in fs::read_dir(path)? {
+        let name = entry?.file_name().to_string_lossy().into_owned();
+        let size = match name
+            .strip_prefix("hugetlb.")
+            .and_then(|n| n.strip_suffix(".current"))
+        {
+            // 跳过hugetlb.<size>.rsvd.current
+            Some(size) if !size.contains('.') => size.to_owned(),
+            _ => continue,
+        };
+        let events = stats::parse_flat_keyed(&path.join(format!("hugetlb.{}.events", size)))?;
+        hugetlb.insert(
+            size,
+            HugeTlbStats {
+                usage: stats::parse_single_value(&path.join(&name))?,
+                max: 0,
+                failcnt: events.get("max").copied().unwrap_or_default(),
+            },
+        );
+    }
+    Ok(hugetlb)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    fn test_io_stats() {
+        let blkio =
+            io_stats("8:0 rbytes=4096 wbytes=8192 rios=1 wios=2 dbytes=0 dios=0\n").unwrap();
+        assert_eq!(blkio.io_service_bytes_recursive.len(), 2);
+        assert_eq!(
+            blkio.io_service_bytes_recursive[1],
+            BlkioEntry {
+                major: 8,
+                minor: 0,
+                op: "Write".to_owned(),
+                value: 8192,
+            }
+        );
+        assert_eq!(blkio.io_serviced_recursive[0].value, 1);
+    }
+}
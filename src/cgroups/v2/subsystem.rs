@@ -33,6 +33,7 @@ pub const SUBSYSTEMLIST: &[SubSystemType] = &[
     SubSystemType::CpuSet,
     SubSystemType::Io,
     SubSystemType::Memory,
+    SubSystemType::HugeTlb,
     SubSystemType::Pids,
 ];
 
@@ -1,5 +1,6 @@
 use clap::Parser;
 use std::path::PathBuf;
+use std::time::Duration;
 
 /// Create a container
 #[derive(Parser, Debug)]
@@ -28,8 +29,221 @@ pub struct Run {
     pub container_id: String,
 }
 
+/// Output the state of a container
 #[derive(Parser, Debug)]
+pub struct State {
+    #[clap(forbid_empty_values = true, required = true)]
+    pub container_id: String,
+}
+
+/// Send a signal to the container's init process
+#[derive(Parser, Debug)]
+pub struct Kill {
+    /// Send the signal to all processes in the container
+    #[clap(short, long)]
+    pub all: bool,
+    #[clap(forbid_empty_values = true, required = true)]
+    pub container_id: String,
+    /// Signal number or name, e.g. 9, KILL or SIGKILL
+    #[clap(default_value = "SIGTERM")]
+    pub signal: String,
+}
+
+/// Delete a container and release its resources
+#[derive(Parser, Debug)]
+pub struct Delete {
+    /// Kill the container first if it is still running
+    #[clap(short, long)]
+    pub force: bool,
+    #[clap(forbid_empty_values = true, required = true)]
+    pub container_id: String,
+}
+
+/// List containers
+#[derive(Parser, Debug)]
+pub struct List {
+    /// Output format
+    #[clap(short, long, default_value = "table", possible_values = &["table", "json"])]
+    pub format: String,
+    /// Only display container IDs
+    #[clap(short, long)]
+    pub quiet: bool,
+}
+
+/// Execute a new process inside a running container
+#[derive(Parser, Debug)]
+#[clap(setting = clap::AppSettings::TrailingVarArg)]
 pub struct Exec {
+    /// Path to a process.json describing the process to run
+    #[clap(short, long)]
+    pub process: Option<PathBuf>,
+    /// Set an environment variable, e.g. --env KEY=VALUE
+    #[clap(short, long, multiple_occurrences = true)]
+    pub env: Vec<String>,
+    /// Working directory inside the container
+    #[clap(long)]
+    pub cwd: Option<PathBuf>,
+    /// User to run the process as, uid[:gid]
+    #[clap(short, long)]
+    pub user: Option<String>,
+    /// Allocate a pseudo-TTY
+    #[clap(short, long)]
+    pub tty: bool,
+    /// Detach from the process after it starts
+    #[clap(short, long)]
+    pub detach: bool,
+    /// File to write the process id to
+    #[clap(long)]
+    pub pid_file: Option<PathBuf>,
+    #[clap(forbid_empty_values = true, required = true)]
+    pub container_id: String,
+    /// Command and arguments to run, ignored when --process is given
+    pub command: Vec<String>,
+}
+
+/// Suspend all processes in the container
+#[derive(Parser, Debug)]
+pub struct Pause {
+    #[clap(forbid_empty_values = true, required = true)]
+    pub container_id: String,
+}
+
+/// Resume all processes of a paused container
+#[derive(Parser, Debug)]
+pub struct Resume {
     #[clap(forbid_empty_values = true, required = true)]
     pub container_id: String,
 }
+
+/// Display the processes running inside a container
+#[derive(Parser, Debug)]
+#[clap(setting = clap::AppSettings::TrailingVarArg)]
+pub struct Ps {
+    /// Output format
+    #[clap(short, long, default_value = "table", possible_values = &["table", "json"])]
+    pub format: String,
+    #[clap(forbid_empty_values = true, required = true)]
+    pub container_id: String,
+    /// Options passed to ps, defaults to -ef
+    #[clap(allow_hyphen_values = true)]
+    pub ps_options: Vec<String>,
+}
+
+/// Display container events such as OOM notifications and resource statistics
+#[derive(Parser, Debug)]
+pub struct Events {
+    /// Display the container's stats once then exit
+    #[clap(long)]
+    pub stats: bool,
+    /// Set the stats collection interval, e.g. 500ms, 5s or 1m
+    #[clap(long, default_value = "5s", parse(try_from_str = parse_duration))]
+    pub interval: Duration,
+    #[clap(forbid_empty_values = true, required = true)]
+    pub container_id: String,
+}
+
+/// Create a new specification file
+#[derive(Parser, Debug)]
+pub struct Spec {
+    /// Directory to write config.json to
+    #[clap(short, long, default_value = ".")]
+    pub bundle: PathBuf,
+    /// Generate a configuration for a rootless container
+    #[clap(long)]
+    pub rootless: bool,
+}
+
+/// Update resource limits of a running container
+#[derive(Parser, Debug)]
+pub struct Update {
+    /// Path to a JSON file with the OCI linux resources to apply, "-" for stdin
+    #[clap(short, long)]
+    pub resources: Option<PathBuf>,
+    /// Memory limit in bytes, with an optional k, m or g suffix, -1 for unlimited
+    #[clap(long, allow_hyphen_values = true, parse(try_from_str = parse_size))]
+    pub memory: Option<i64>,
+    /// CPU CFS quota in microseconds, -1 for unlimited
+    #[clap(long, allow_hyphen_values = true)]
+    pub cpu_quota: Option<i64>,
+    /// CPU CFS period in microseconds
+    #[clap(long)]
+    pub cpu_period: Option<u64>,
+    /// CPU shares, a relative weight against other containers
+    #[clap(long)]
+    pub cpu_shares: Option<u64>,
+    /// Maximum number of processes, -1 for unlimited
+    #[clap(long, allow_hyphen_values = true)]
+    pub pids_limit: Option<i64>,
+    /// CPUs the container may run on, e.g. 0-3 or 0,2
+    #[clap(long)]
+    pub cpuset_cpus: Option<String>,
+    #[clap(forbid_empty_values = true, required = true)]
+    pub container_id: String,
+}
+
+//解析带k, m, g单位的字节数, -1表示不限制
+fn parse_size(s: &str) -> Result<i64, String> {
+    if s == "-1" {
+        return Ok(-1);
+    }
+    let lower = s.to_ascii_lowercase();
+    let lower = lower.trim_end_matches('b');
+    let (value, shift) = match lower.char_indices().last() {
+        Some((i, 'k')) => (&lower[..i], 10),
+        Some((i, 'm')) => (&lower[..i], 20),
+        Some((i, 'g')) => (&lower[..i], 30),
+        _ => (lower, 0),
+    };
+    let value: i64 = value.parse().map_err(|_| format!("invalid size {:?}", s))?;
+    value
+        .checked_mul(1 << shift)
+        .filter(|v| *v >= 0)
+        .ok_or_else(|| format!("invalid size {:?}", s))
+}
+
+//解析带单位的时间间隔, 不带单位时按秒处理
+fn parse_duration(s: &str) -> Result<Duration, String> {
+    let (value, unit) = match s.find(|c: char| !c.is_ascii_digit()) {
+        Some(i) => s.split_at(i),
+        None => (s, "s"),
+    };
+    let value: u64 = value
+        .parse()
+        .map_err(|_| format!("invalid duration {:?}", s))?;
+    let duration = match unit {
+        "ms" => Duration::from_millis(value),
+        "s" => Duration::from_secs(value),
+        "m" => Duration::from_secs(value * 60),
+        _ => return Err(format!("invalid duration unit {:?}", unit)),
+    };
+    if duration.is_zero() {
+        return Err("duration must be greater than 0".to_owned());
+    }
+    Ok(duration)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    fn test_parse_duration() {
+        assert_eq!(parse_duration("500ms"), Ok(Duration::from_millis(500)));
+        assert_eq!(parse_duration("5s"), Ok(Duration::from_secs(5)));
+        assert_eq!(parse_duration("2"), Ok(Duration::from_secs(2)));
+        assert_eq!(parse_duration("1m"), Ok(Duration::from_secs(60)));
+        assert!(parse_duration("0s").is_err());
+        assert!(parse_duration("5h").is_err());
+        assert!(parse_duration("s").is_err());
+    }
+
+    #[test]
+    fn test_parse_size() {
+        assert_eq!(parse_size("1024"), Ok(1024));
+        assert_eq!(parse_size("4k"), Ok(4096));
+        assert_eq!(parse_size("512m"), Ok(512 << 20));
+        assert_eq!(parse_size("1GB"), Ok(1 << 30));
+        assert_eq!(parse_size("-1"), Ok(-1));
+        assert!(parse_size("-2").is_err());
+        assert!(parse_size("1t").is_err());
+    }
+}
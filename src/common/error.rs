@@ -1,12 +0,0 @@
-#[derive(Debug, Clone)]
-pub enum Error {
-    Str(String),
-}
-
-impl std::fmt::Display for Error {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{}", self)
-    }
-}
-
-impl std::error::Error for Error {}
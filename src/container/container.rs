@@ -1,26 +1,44 @@
+use super::idmap;
+use super::mount;
+use super::namespace;
+use super::rootless;
+use super::security;
 use super::state::{State, Status};
 use crate::cgroups::common::ControllerOpt;
-use crate::cgroups::CgroupManager;
-use crate::cgroups::CgroupVersion;
-use crate::cgroups::{v1, v2};
-use crate::oci::oci::{Namespace, NamespaceType, Spec};
-use crate::utils::fork::fork_child;
+use crate::cgroups::{self, systemd};
+use crate::cgroups::{CgroupManager, FreezerState};
+use crate::oci::oci::{LinuxResources, Namespace, NamespaceType, Process, Spec, User};
+use crate::utils::fork::{fork_child, set_child_subreaper};
 use crate::utils::fs;
 use crate::utils::ipc;
 use crate::utils::ipc::{NotifyListener, NotifySocket};
 use crate::utils::ipc::{Reader, Writer};
 use anyhow::{bail, Context, Result};
+use nix::errno::Errno;
 use nix::mount::{mount, umount2, MntFlags, MsFlags};
-use nix::sched::{unshare, CloneFlags};
-use nix::sys::statfs::statfs;
-use nix::sys::statfs::{CGROUP2_SUPER_MAGIC, TMPFS_MAGIC};
-use nix::unistd::Pid;
+use nix::sys::signal::{kill, Signal};
+use nix::sys::wait::{waitpid, WaitStatus};
+use nix::unistd::{setgroups, setresgid, setresuid, Gid, Pid, Uid, User as UnixUser};
 
-use nix::unistd::{chdir, execv, pivot_root, sethostname};
+use nix::unistd::{chdir, execve, pivot_root, sethostname};
+use serde::{Deserialize, Serialize};
 use std::ffi::CString;
+use std::os::unix::ffi::OsStrExt;
+use std::os::unix::fs::MetadataExt;
+use std::time::Duration;
 use std::{path::Path, path::PathBuf};
-const SOCK_FILE: &'static str = "smog.sock";
-pub const DEFAULT_CGROUP_ROOT: &str = "/sys/fs/cgroup";
+const SOCK_FILE: &str = "smog.sock";
+const DEFAULT_PATH_ENV: &str = "/usr/local/sbin:/usr/local/bin:/usr/sbin:/usr/bin:/sbin:/bin";
+
+//容器创建和exec过程中父子进程之间的同步消息
+#[derive(Serialize, Deserialize, Debug)]
+pub enum SyncMessage {
+    UserMapRequest,
+    UserMapDone,
+    CgroupJoined,
+    ChildPid(i32),
+    Ready,
+}
 
 pub struct ContainerInstance {
     pub state: State,
@@ -30,15 +48,15 @@ pub struct ContainerInstance {
 impl ContainerInstance {
     fn new(state: State, container_dir: &Path) -> Self {
         Self {
-            state: state,
+            state,
             dir: container_dir.to_path_buf(),
         }
     }
 
     fn load(container_dir: &Path) -> Result<ContainerInstance> {
-        let state = State::load(&container_dir)?;
+        let state = State::load(container_dir)?;
         Ok(Self {
-            state: state,
+            state,
             dir: container_dir.to_path_buf(),
         })
     }
@@ -48,6 +66,147 @@ impl ContainerInstance {
         Ok(())
     }
 
+    pub fn load_spec(&self) -> Result<Spec> {
+        Spec::load(self.state.bundle.join("config.json"))
+    }
+
+    //状态目录的所有者, 即创建容器的用户
+    pub fn owner(&self) -> Result<String> {
+        let uid = Uid::from_raw(std::fs::metadata(&self.dir)?.uid());
+        Ok(match UnixUser::from_uid(uid)? {
+            Some(user) => user.name,
+            None => uid.to_string(),
+        })
+    }
+
+    //根据init进程是否还存在刷新状态, 进程退出后即使state.json中是running也认为已经stopped,
+    //pid被其他进程复用时启动时间不同, 同样认为已经stopped
+    pub fn refresh_status(&mut self) -> Result<()> {
+        if self.state.status == Status::Stopped {
+            return Ok(());
+        }
+        if !process_alive(self.state.pid, self.state.pid_start_time) {
+            self.state.status = Status::Stopped;
+            self.save()?;
+        }
+        Ok(())
+    }
+
+    //向容器的init进程发送信号, all为true时发送给容器cgroup中的所有进程
+    pub fn kill(&mut self, signal: Signal, all: bool) -> Result<()> {
+        self.refresh_status()?;
+        match self.state.status {
+            Status::Created | Status::Running | Status::Paused => {}
+            _ => bail!(
+                "container {} is {}, can't be killed",
+                self.state.id,
+                self.state.status
+            ),
+        }
+        if all {
+            let manager = self.cgroup_manager()?;
+            for pid in manager.get_all_pids()? {
+                match kill(pid, signal) {
+                    // 进程可能已经退出
+                    Ok(_) | Err(Errno::ESRCH) => {}
+                    Err(err) => bail!("failed to kill {}: {}", pid, err),
+                }
+            }
+        } else {
+            kill(Pid::from_raw(self.state.pid), signal)?;
+        }
+        // 被冻结的进程在解冻之前不会处理SIGKILL, 其他信号则等到resume时再处理
+        if signal == Signal::SIGKILL && self.state.status == Status::Paused {
+            self.thaw()?;
+        }
+        Ok(())
+    }
+
+    //旧版本创建的容器状态中没有cgroups_path, 使用默认路径
+    pub fn cgroup_manager(&self) -> Result<Box<dyn CgroupManager>> {
+        let cgroups_path = match &self.state.cgroups_path {
+            Some(path) => path.clone(),
+            None => cgroups::cgroups_path(None, &self.state.id)?,
+        };
+        new_cgroup_manager(&cgroups_path, self.state.systemd_cgroup)
+    }
+
+    //容器cgroup中的所有进程
+    pub fn pids(&self) -> Result<Vec<Pid>> {
+        self.cgroup_manager()?.get_all_pids()
+    }
+
+    //把新的资源限制与当前生效的限制合并, 重新应用到cgroup并保存到状态中
+    pub fn update(&mut self, resources: &LinuxResources) -> Result<()> {
+        self.refresh_status()?;
+        if self.state.status == Status::Stopped {
+            bail!("container {} is stopped, can't be updated", self.state.id);
+        }
+        let mut effective = self.state.resources.clone().unwrap_or_default();
+        effective.merge(resources)?;
+        self.cgroup_manager()?.apply(&ControllerOpt {
+            resources: &effective,
+        })?;
+        self.state.resources = Some(effective);
+        self.save()
+    }
+
+    //冻结容器中的所有进程
+    pub fn pause(&mut self) -> Result<()> {
+        self.refresh_status()?;
+        if self.state.status != Status::Running {
+            bail!(
+                "container {} is {}, only running containers can be paused",
+                self.state.id,
+                self.state.status
+            );
+        }
+        self.cgroup_manager()?.freeze(FreezerState::Frozen)?;
+        self.state.status = Status::Paused;
+        self.save()
+    }
+
+    pub fn resume(&mut self) -> Result<()> {
+        self.refresh_status()?;
+        if self.state.status != Status::Paused {
+            bail!(
+                "container {} is {}, only paused containers can be resumed",
+                self.state.id,
+                self.state.status
+            );
+        }
+        self.thaw()
+    }
+
+    fn thaw(&mut self) -> Result<()> {
+        self.cgroup_manager()?.freeze(FreezerState::Thawed)?;
+        self.state.status = Status::Running;
+        self.save()
+    }
+
+    //停止容器的init进程, 运行中或暂停的容器只有force时才会被杀掉
+    fn stop(&mut self, force: bool) -> Result<()> {
+        self.refresh_status()?;
+        match self.state.status {
+            Status::Stopped => return Ok(()),
+            Status::Running | Status::Paused if !force => bail!(
+                "container {} is {}, use --force to delete it",
+                self.state.id,
+                self.state.status
+            ),
+            _ => {}
+        }
+        let pid = Pid::from_raw(self.state.pid);
+        match kill(pid, Signal::SIGKILL) {
+            Ok(_) | Err(Errno::ESRCH) => {}
+            Err(err) => bail!("failed to kill {}: {}", pid, err),
+        }
+        if self.state.status == Status::Paused {
+            self.thaw()?;
+        }
+        wait_for_exit(pid)
+    }
+
     pub fn start(&mut self) -> Result<()> {
         let socket_path = self.dir.join(SOCK_FILE);
         let notify_socket = NotifySocket::new(&socket_path)?;
@@ -63,29 +222,96 @@ pub struct Container {
     container_id: String,
     bundle: PathBuf,
     root_path: PathBuf,
+    //通过systemd管理容器的cgroup
+    systemd_cgroup: bool,
 }
 
 impl Container {
     const ROOT_PATH: &'static str = "/var/run/smog";
 
     pub fn new(container_id: String, bundle: PathBuf) -> Self {
-        let root_path = PathBuf::from(Self::ROOT_PATH);
+        let root_path = Self::root_path();
         Self {
-            container_id: container_id,
-            bundle: bundle,
-            root_path: root_path,
+            container_id,
+            bundle,
+            root_path,
+            systemd_cgroup: false,
+        }
+    }
+
+    //容器状态的根目录, rootless模式下在用户自己的运行时目录中
+    fn root_path() -> PathBuf {
+        if rootless::is_rootless() {
+            rootless::root_path()
+        } else {
+            PathBuf::from(Self::ROOT_PATH)
         }
     }
 
     pub fn load(container_id: String) -> Result<ContainerInstance> {
-        let root_path = PathBuf::from(Self::ROOT_PATH);
+        let root_path = Self::root_path();
         let container_dir = root_path.join(container_id);
         let container = ContainerInstance::load(&container_dir)?;
         Ok(container)
     }
 
-    fn set_bundle(mut self, bundle: PathBuf) -> Self {
-        self.bundle = bundle;
+    //列出状态根目录中的所有容器, 加载失败的容器同样返回, 由调用方决定如何报告
+    pub fn list() -> Result<Vec<(String, Result<ContainerInstance>)>> {
+        let root_path = Self::root_path();
+        if !root_path.exists() {
+            return Ok(Vec::new());
+        }
+        let mut containers = Vec::new();
+        for entry in std::fs::read_dir(&root_path)? {
+            let entry = entry?;
+            if !entry.file_type()?.is_dir() {
+                continue;
+            }
+            let id = entry.file_name().to_string_lossy().into_owned();
+            containers.push((id, ContainerInstance::load(&entry.path())));
+        }
+        containers.sort_by(|a, b| a.0.cmp(&b.0));
+        Ok(containers)
+    }
+
+    //删除容器: 停止容器进程, 删除cgroup, 状态目录和其中的socket文件
+    //创建到一半失败的容器可能没有state.json, 也需要能够删除, 此时不知道cgroup路径, 不清理cgroup
+    pub fn delete(container_id: String, force: bool) -> Result<()> {
+        let container_dir = Self::root_path().join(&container_id);
+        if !container_dir.exists() {
+            bail!("container {} does not exist", container_id);
+        }
+        if State::exists(&container_dir) {
+            let mut container = ContainerInstance::load(&container_dir)?;
+            container.stop(force)?;
+            let manager = container.cgroup_manager()?;
+            // cgroup中还有其他进程时无法删除, 只有force时才杀掉它们
+            if let Ok(pids) = manager.get_all_pids() {
+                if !pids.is_empty() && !force {
+                    bail!(
+                        "container {} still has processes in its cgroup, use --force to delete it",
+                        container_id
+                    );
+                }
+                for pid in pids {
+                    let _ = kill(pid, Signal::SIGKILL);
+                    wait_for_exit(pid)?;
+                }
+            }
+            manager.remove()?;
+            if let (Some(path), Some(created)) = (
+                &container.state.cgroups_path,
+                &container.state.created_cgroups_path,
+            ) {
+                cgroups::remove_created_parents(path, created)?;
+            }
+        }
+        fs::remove_dir_all(&container_dir)?;
+        Ok(())
+    }
+
+    pub fn set_systemd_cgroup(mut self, systemd_cgroup: bool) -> Self {
+        self.systemd_cgroup = systemd_cgroup;
         self
     }
 
@@ -102,102 +328,222 @@ impl Container {
     }
 
     pub fn create(self) -> Result<(ContainerInstance, Pid)> {
-        let spec = self.load_spec()?;
-        //spec.linux.and_then()
+        let mut spec = self.load_spec()?;
+        // OCI state中的bundle需要是绝对路径, 相对路径的bind mount也基于它
+        let bundle = std::fs::canonicalize(&self.bundle)?;
+        let rootless = rootless::is_rootless();
+        if rootless {
+            rootless::adjust_spec(&mut spec)?;
+        }
         let linux = spec.linux.as_ref().context("no linux in spec")?;
 
         let container_dir = self.create_container_dir()?;
         let sock_path = container_dir.join(SOCK_FILE);
         let notify_listener = NotifyListener::new(&sock_path)?;
-        let (w_ipc, r_ipc) = ipc::new::<String>()?;
+        let (w_ipc, r_ipc) = ipc::new::<SyncMessage>()?;
+        let (w_ack, r_ack) = ipc::new::<SyncMessage>()?;
         let namespaces: Vec<Namespace> = match &spec.linux {
-            Some(linux) => linux.namespaces.clone().unwrap_or(Vec::new()),
+            Some(linux) => linux.namespaces.clone().unwrap_or_default(),
             None => Vec::new(),
         };
-        let manager = new_cgroup_manager(&self.container_id)?;
+        // spec中设置了资源限制时cgroup必须生效, 否则rootless模式下可以没有cgroup
+        let required = linux.resources.as_ref().is_some_and(|r| r.has_limits());
+        let new_cgroup = || -> Result<_> {
+            let cgroups_path = if self.systemd_cgroup {
+                systemd::unit_path(linux.cgroups_path.as_deref(), &self.container_id, rootless)?
+            } else {
+                cgroups::cgroups_path(linux.cgroups_path.as_deref(), &self.container_id)?
+            };
+            let manager = new_cgroup_manager(&cgroups_path, self.systemd_cgroup)?;
+            // systemd驱动的cgroup由systemd创建和删除
+            let created_cgroups_path = match self.systemd_cgroup {
+                true => None,
+                false => Some(cgroups::created_prefix(&cgroups_path)?),
+            };
+            Ok((cgroups_path, manager, created_cgroups_path))
+        };
+        let cgroup = rootless::tolerate_cgroup_error(new_cgroup(), required)?;
 
-        let pid = fork_child(|| init_process(&w_ipc, &spec, &notify_listener, &namespaces))?;
-        manager.add_task(pid)?;
-        if let Some(r) = linux.resources.as_ref() {
-            manager.apply(&ControllerOpt { resources: r })?;
+        // run时需要waitpid容器init进程, 而它是孙进程
+        set_child_subreaper()?;
+        let intermediate = fork_child(|| {
+            intermediate_process(
+                &w_ipc,
+                &r_ack,
+                &spec,
+                &bundle,
+                &notify_listener,
+                &namespaces,
+            )
+        })?;
+        // 关闭父进程中的写端, 子进程异常退出时read才不会一直阻塞
+        w_ipc.close()?;
+        let mut msg = r_ipc.read()?;
+        if let SyncMessage::UserMapRequest = msg {
+            idmap::write_mappings(
+                intermediate,
+                linux.uid_mappings.as_deref().unwrap_or_default(),
+                linux.gid_mappings.as_deref().unwrap_or_default(),
+            )?;
+            w_ack.write(SyncMessage::UserMapDone)?;
+            msg = r_ipc.read()?;
+        }
+        let pid = match msg {
+            SyncMessage::ChildPid(pid) => Pid::from_raw(pid),
+            msg => bail!("unexpected message {:?}, want child pid", msg),
+        };
+        match waitpid(intermediate, None)? {
+            WaitStatus::Exited(_, 0) => {}
+            status => bail!("intermediate process failed: {:?}", status),
+        }
+        if let Some((_, manager, _)) = &cgroup {
+            let cgroup_ret = manager
+                .add_task(pid)
+                .and_then(|_| match linux.resources.as_ref() {
+                    Some(r) => manager.apply(&ControllerOpt { resources: r }),
+                    None => Ok(()),
+                });
+            rootless::tolerate_cgroup_error(cgroup_ret, required)?;
         }
-        let msg = r_ipc.read()?;
-        if msg != "ready" {
-            bail!("not ready");
+        w_ack.write(SyncMessage::CgroupJoined)?;
+        match r_ipc.read()? {
+            SyncMessage::Ready => {}
+            msg => bail!("unexpected message {:?}, want ready", msg),
         }
-        let mut state = State::new(&self.container_id, pid.as_raw(), self.bundle);
+        r_ipc.close()?;
+        let mut state = State::new(&self.container_id, pid.as_raw(), bundle);
         state.status = Status::Created;
+        state.pid_start_time = Some(procfs::process::Process::new(pid.as_raw())?.stat.starttime);
+        state.resources = linux.resources.clone();
+        if let Some((cgroups_path, _, created_cgroups_path)) = cgroup {
+            state.cgroups_path = Some(cgroups_path);
+            state.created_cgroups_path = created_cgroups_path;
+        }
+        state.systemd_cgroup = self.systemd_cgroup;
         let container = ContainerInstance::new(state, &container_dir);
         container.save()?;
         Ok((container, pid))
     }
+}
 
-    pub fn run(self) -> Result<()> {
-        Ok(())
+fn wait_for_exit(pid: Pid) -> Result<()> {
+    for _ in 0..100 {
+        if !process_alive(pid.as_raw(), None) {
+            return Ok(());
+        }
+        std::thread::sleep(Duration::from_millis(100));
     }
+    bail!("timeout waiting for process {} to exit", pid)
 }
 
-fn get_cgroup_version() -> Result<CgroupVersion> {
-    let default_root = Path::new(DEFAULT_CGROUP_ROOT);
-    return match default_root.exists() {
-        true => {
-            let stat = statfs(default_root)?;
-            match stat.filesystem_type() {
-                CGROUP2_SUPER_MAGIC => Ok(CgroupVersion::V2),
-                TMPFS_MAGIC => Ok(CgroupVersion::V2),
-                _ => bail!("non default cgroup root not supported"),
-            }
-        }
-        false => {
-            bail!("non default cgroup root not supported")
-        }
-    };
+//僵尸进程也认为已经退出, 指定了start_time时还需要启动时间相同
+fn process_alive(pid: i32, start_time: Option<u64>) -> bool {
+    match procfs::process::Process::new(pid) {
+        Ok(p) => p.stat.state != 'Z' && start_time.is_none_or(|t| t == p.stat.starttime),
+        Err(_) => false,
+    }
 }
 
-fn new_cgroup_manager(container_id: &str) -> Result<Box<dyn CgroupManager>> {
-    let m: Box<dyn CgroupManager> = match get_cgroup_version()? {
-        CgroupVersion::V1 => Box::new(v1::manager::Manager::new(container_id)),
-        CgroupVersion::V2 => Box::new(v2::manager::Manager::new(
-            DEFAULT_CGROUP_ROOT.into(),
-            container_id,
-        )),
-    };
-    Ok(m)
+//systemd驱动时cgroups_path为slice:prefix:name, 否则是相对于cgroup根目录的路径
+pub fn new_cgroup_manager(cgroups_path: &Path, systemd: bool) -> Result<Box<dyn CgroupManager>> {
+    if systemd {
+        let manager = systemd::Manager::new(cgroups_path, rootless::is_rootless())?;
+        return Ok(Box::new(manager));
+    }
+    cgroups::fs_manager(cgroups_path)
+}
+
+//中间进程: user namespace需要最先加入, 而unshare/setns pid namespace只对之后fork出的子进程生效,
+//所以在这里进入这两个namespace后再fork出容器的init进程(新pid namespace中的1号进程),
+//并把它在父进程pid namespace中的pid发给父进程
+fn intermediate_process(
+    w: &Writer<SyncMessage>,
+    r: &Reader<SyncMessage>,
+    spec: &Spec,
+    bundle: &Path,
+    notify_listener: &NotifyListener,
+    namespaces: &[Namespace],
+) -> Result<()> {
+    if let Some(ns) = namespace::find(namespaces, &NamespaceType::User) {
+        namespace::enter(ns)?;
+        // 新建的user namespace需要父进程写入uid_map和gid_map
+        if ns.path.is_none() {
+            w.write(SyncMessage::UserMapRequest)?;
+            match r.read()? {
+                SyncMessage::UserMapDone => {}
+                msg => bail!("unexpected message {:?}, want user map done", msg),
+            }
+        }
+    }
+    if let Some(ns) = namespace::find(namespaces, &NamespaceType::Pid) {
+        namespace::enter(ns)?;
+    }
+    let pid = fork_child(|| init_process(w, r, spec, bundle, notify_listener, namespaces))?;
+    w.write(SyncMessage::ChildPid(pid.as_raw()))?;
+    Ok(())
 }
 
 fn init_process(
-    w: &Writer<String>,
+    w: &Writer<SyncMessage>,
+    r: &Reader<SyncMessage>,
     spec: &Spec,
+    bundle: &Path,
     notify_listener: &NotifyListener,
-    namespaces: &Vec<Namespace>,
+    namespaces: &[Namespace],
 ) -> Result<()> {
-    for v in namespaces.iter() {
-        match v.typ {
+    // 等待父进程把自己加入容器的cgroup, 之后创建的cgroup namespace才以容器的cgroup为根
+    match r.read()? {
+        SyncMessage::CgroupJoined => {}
+        msg => bail!("unexpected message {:?}, want cgroup joined", msg),
+    }
+    // 进入cgroup namespace之后/proc/self/cgroup中的路径是相对的, 需要提前读取
+    let mounts = spec.mounts.as_deref().unwrap_or_default();
+    let cgroup_dirs = match mounts.iter().any(|m| m.typ.as_deref() == Some("cgroup")) {
+        true => cgroups::own_hierarchy_dirs()?,
+        false => Vec::new(),
+    };
+    for ns in namespaces.iter() {
+        match ns.typ {
+            // user和pid namespace已经在中间进程中进入, mount namespace最后进入
+            NamespaceType::User | NamespaceType::Pid | NamespaceType::Mount => {}
             NamespaceType::Uts => {
-                unshare(CloneFlags::CLONE_NEWUTS)?;
-                sethostname("container")?;
+                namespace::enter(ns)?;
+                if ns.path.is_none() {
+                    sethostname(spec.hostname.as_deref().unwrap_or("container"))?;
+                }
             }
-            NamespaceType::Ipc => {
-                unshare(CloneFlags::CLONE_NEWIPC)?;
-            }
-            NamespaceType::User => {
-                unshare(CloneFlags::CLONE_NEWUSER)?;
+            _ => namespace::enter(ns)?,
+        }
+    }
+    if let Some(ns) = namespace::find(namespaces, &NamespaceType::Mount) {
+        namespace::enter(ns)?;
+        // 加入已有的mount namespace时根目录已经是对应容器的rootfs
+        if ns.path.is_none() {
+            let root = spec.root.as_ref().context("no root in spec")?;
+            prepare_roofs(&root.path)?;
+            for m in mounts {
+                mount::mount_to_rootfs(m, &root.path, bundle, &cgroup_dirs)?;
             }
-            NamespaceType::Mount => {
-                unshare(CloneFlags::CLONE_NEWNS)?;
-                let ref rootfs = spec.root.as_ref().unwrap().path;
-                prepare_roofs(rootfs)?;
-                pivot_rootfs(rootfs)?;
+            // 在proc, sys等挂载完成之后再遮盖或设置只读
+            if let Some(linux) = &spec.linux {
+                for path in linux.masked_paths.iter().flatten() {
+                    mount::mask_path(&root.path, Path::new(path))?;
+                }
+                for path in linux.readonly_paths.iter().flatten() {
+                    mount::readonly_path(&root.path, Path::new(path))?;
+                }
             }
-            NamespaceType::Network => {
-                unshare(CloneFlags::CLONE_NEWNET)?;
+            pivot_rootfs(&root.path)?;
+            if root.readonly {
+                mount::remount_readonly(Path::new("/"))?;
             }
-            _ => {}
         }
     }
-    w.write("ready".to_owned())?;
+    let process = spec.process.as_ref().context("no process in spec")?;
+    w.write(SyncMessage::Ready)?;
     notify_listener.wait_container_start()?;
-    do_exec("/bin/sh")?;
+    notify_listener.close()?;
+    do_exec(process)?;
     Ok(())
 }
 
@@ -220,26 +566,69 @@ fn prepare_roofs(rootfs: &Path) -> Result<()> {
 //povit_root的新目录不能和原来的root目录在一个文件系统上
 fn pivot_rootfs(rootfs: &Path) -> Result<()> {
     chdir(rootfs)?;
-    let old_root = rootfs.join("oldroot");
-    fs::create_dir_all(&old_root)?;
-    pivot_root(rootfs.as_os_str(), old_root.as_os_str())?;
-    umount2("./oldroot", MntFlags::MNT_DETACH)?;
-    fs::remove_dir_all("./oldroot")?;
+    // new_root和put_old都使用".", 旧的root被叠在新root之下, 不需要在rootfs中创建目录(rootless时可能没有写权限)
+    pivot_root(".", ".")?;
+    umount2(".", MntFlags::MNT_DETACH)?;
     chdir("/")?;
     Ok(())
 }
 
-fn do_exec(cmd: &str) -> Result<()> {
-    let mut args: Vec<CString> = Vec::new();
-    args.push(CString::new(cmd).unwrap());
-    match execv(&CString::new(cmd).unwrap(), &args) {
-        Ok(_) => (),
-        Err(err) => {
-            // We can't log this error because it doesn't see the log file
-            println!("[ERROR]: {}", err.to_string());
-            std::process::exit(1);
-        }
+//执行spec中的process, 此时已经pivot_root, PATH查找在容器rootfs中进行
+pub fn do_exec(process: &Process) -> Result<()> {
+    let args = match &process.args {
+        Some(args) if !args.is_empty() => args,
+        _ => bail!("no args in process"),
+    };
+    let env = process.env.clone().unwrap_or_default();
+    security::set_rlimits(process.rlimits.as_deref().unwrap_or_default())?;
+    // bounding set需要在切换用户之前设置, 其余capability在切换用户和chdir之后设置
+    if let Some(capabilities) = &process.capabilities {
+        security::drop_bounding_set(capabilities)?;
+        security::keep_capabilities(true)?;
+    }
+    set_user(&process.user)?;
+    chdir(&process.cwd).with_context(|| format!("failed to chdir to {:?}", process.cwd))?;
+    if let Some(capabilities) = &process.capabilities {
+        security::keep_capabilities(false)?;
+        security::set_capabilities(capabilities)?;
+    }
+    if process.no_new_privileges == Some(true) {
+        security::set_no_new_privileges()?;
+    }
+    let path = fs::find_executable(&args[0], process.path_env().unwrap_or(DEFAULT_PATH_ENV))?;
+    let path = CString::new(path.as_os_str().as_bytes())?;
+    let args = args
+        .iter()
+        .map(|a| CString::new(a.as_str()))
+        .collect::<Result<Vec<_>, _>>()?;
+    // execve只使用env中的环境变量, 不会继承runtime的环境
+    let env = env
+        .iter()
+        .map(|e| CString::new(e.as_str()))
+        .collect::<Result<Vec<_>, _>>()?;
+    execve(&path, &args, &env)?;
+    Ok(())
+}
+
+//切换到process中指定的用户, 先设置附加组和gid, 最后设置uid
+fn set_user(user: &User) -> Result<()> {
+    let gids: Vec<Gid> = user
+        .additional_gids
+        .as_deref()
+        .unwrap_or_default()
+        .iter()
+        .map(|g| Gid::from_raw(*g))
+        .collect();
+    match setgroups(&gids) {
+        Ok(_) => {}
+        // rootless模式下setgroups被禁止, 没有附加组时忽略
+        Err(Errno::EPERM) if gids.is_empty() => {}
+        Err(err) => bail!("failed to set additional gids: {}", err),
     }
+    let gid = Gid::from_raw(user.gid);
+    setresgid(gid, gid, gid).with_context(|| format!("failed to set gid {}", gid))?;
+    let uid = Uid::from_raw(user.uid);
+    setresuid(uid, uid, uid).with_context(|| format!("failed to set uid {}", uid))?;
     Ok(())
 }
 
@@ -254,6 +643,15 @@ mod tests {
         println!("{:?}", s);
     }
     #[test]
+    fn test_process_alive() {
+        let me = procfs::process::Process::myself().unwrap();
+        assert!(process_alive(me.pid, None));
+        assert!(process_alive(me.pid, Some(me.stat.starttime)));
+        assert!(!process_alive(me.pid, Some(me.stat.starttime + 1)));
+    }
+
+    #[test]
+    #[ignore = "needs root, a rootfs in the repo bundle and cgroups matching its config.json"]
     fn test_create_container_status() {
         Container::new(
             "aabbcc".to_owned(),
@@ -0,0 +1,124 @@
+use super::container::ContainerInstance;
+use super::state::Status;
+use crate::cgroups::stats::Stats;
+use crate::cgroups::{CgroupManager, OomNotifier};
+use anyhow::{bail, Result};
+use nix::errno::Errno;
+use nix::poll::{poll, PollFd, PollFlags};
+use serde::Serialize;
+use std::thread;
+use std::time::{Duration, Instant};
+
+//events命令每行输出一个事件
+#[derive(Serialize, Debug)]
+pub struct Event {
+    #[serde(rename = "type")]
+    pub typ: &'static str,
+    pub id: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub data: Option<Stats>,
+}
+
+impl Event {
+    fn emit(&self) -> Result<()> {
+        println!("{}", serde_json::to_string(self)?);
+        Ok(())
+    }
+}
+
+//持续输出容器的事件
+pub struct EventsWatcher {
+    container: ContainerInstance,
+    manager: Box<dyn CgroupManager>,
+    interval: Duration,
+}
+
+impl EventsWatcher {
+    pub fn new(container: ContainerInstance, interval: Duration) -> Result<Self> {
+        let manager = container.cgroup_manager()?;
+        Ok(Self {
+            container,
+            manager,
+            interval,
+        })
+    }
+
+    //只输出一次统计数据
+    pub fn stats(mut self) -> Result<()> {
+        self.ensure_not_stopped()?;
+        self.stats_event()?.emit()
+    }
+
+    //每隔interval输出一次统计数据, 期间发生OOM时立即输出oom事件, 容器停止后退出
+    pub fn watch(mut self) -> Result<()> {
+        self.ensure_not_stopped()?;
+        // 没有memory cgroup时只输出统计数据
+        let mut notifier = match self.manager.oom_notifier() {
+            Ok(notifier) => Some(notifier),
+            Err(err) => {
+                eprintln!("oom events are not available: {}", err);
+                None
+            }
+        };
+        loop {
+            self.container.refresh_status()?;
+            if self.container.state.status == Status::Stopped {
+                return Ok(());
+            }
+            self.stats_event()?.emit()?;
+            let deadline = Instant::now() + self.interval;
+            self.wait_oom(&mut notifier, deadline)?;
+        }
+    }
+
+    fn wait_oom(
+        &self,
+        notifier: &mut Option<Box<dyn OomNotifier>>,
+        deadline: Instant,
+    ) -> Result<()> {
+        loop {
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            if remaining.is_zero() {
+                return Ok(());
+            }
+            let notifier = match notifier {
+                Some(notifier) => notifier,
+                None => {
+                    thread::sleep(remaining);
+                    return Ok(());
+                }
+            };
+            let timeout = remaining.as_millis().min(i32::MAX as u128) as i32;
+            let mut fds = [PollFd::new(notifier.as_raw_fd(), PollFlags::POLLIN)];
+            match poll(&mut fds, timeout) {
+                Ok(0) | Err(Errno::EINTR) => continue,
+                Ok(_) => {}
+                Err(err) => return Err(err.into()),
+            }
+            if notifier.read()? > 0 {
+                Event {
+                    typ: "oom",
+                    id: self.container.state.id.clone(),
+                    data: None,
+                }
+                .emit()?;
+            }
+        }
+    }
+
+    fn stats_event(&self) -> Result<Event> {
+        Ok(Event {
+            typ: "stats",
+            id: self.container.state.id.clone(),
+            data: Some(self.manager.stats()?),
+        })
+    }
+
+    fn ensure_not_stopped(&mut self) -> Result<()> {
+        self.container.refresh_status()?;
+        if self.container.state.status == Status::Stopped {
+            bail!("container {} is stopped", self.container.state.id);
+        }
+        Ok(())
+    }
+}
@@ -1,7 +1,134 @@
+use super::container::{do_exec, ContainerInstance, SyncMessage};
+use super::namespace;
+use super::rootless;
+use super::state::Status;
+use crate::oci::oci::Process;
+use crate::utils::fork::fork_child;
+use crate::utils::ipc::{self, Reader, Writer};
+use crate::utils::tty;
+use anyhow::{bail, Result};
+use nix::pty::{openpty, OpenptyResult};
+use nix::sys::signal::{kill, Signal};
+use nix::sys::wait::{waitpid, WaitStatus};
+use nix::unistd::{close, Pid};
+use std::path::PathBuf;
+
+//在运行中的容器里执行新的进程
 pub struct ExecContainer {
-    container_id: String,
+    container: ContainerInstance,
+    process: Process,
+    detach: bool,
+    pid_file: Option<PathBuf>,
 }
 
 impl ExecContainer {
-    fn new() {}
+    pub fn new(
+        container: ContainerInstance,
+        process: Process,
+        detach: bool,
+        pid_file: Option<PathBuf>,
+    ) -> Self {
+        Self {
+            container,
+            process,
+            detach,
+            pid_file,
+        }
+    }
+
+    //返回进程的退出码, detach时进程启动后立即返回0
+    pub fn exec(mut self) -> Result<i32> {
+        self.container.refresh_status()?;
+        if self.container.state.status != Status::Running {
+            bail!(
+                "container {} is {}, can't exec",
+                self.container.state.id,
+                self.container.state.status
+            );
+        }
+        if self.process.terminal && self.detach {
+            bail!("tty is not supported in detach mode");
+        }
+        let target = Pid::from_raw(self.container.state.pid);
+        let pty = match self.process.terminal {
+            true => Some(openpty(None, None)?),
+            false => None,
+        };
+        let (w_ipc, r_ipc) = ipc::new::<SyncMessage>()?;
+        let (w_ack, r_ack) = ipc::new::<SyncMessage>()?;
+        let child = fork_child(|| {
+            exec_process(
+                &self.process,
+                target,
+                self.detach,
+                &w_ipc,
+                &r_ack,
+                pty.as_ref(),
+            )
+        })?;
+        w_ipc.close()?;
+        if let Some(pty) = &pty {
+            close(pty.slave)?;
+        }
+        // 中间进程在fork之前加入容器的cgroup, exec的进程会继承
+        let manager = self.container.cgroup_manager()?;
+        if let Err(err) = rootless::tolerate_cgroup_error(manager.add_task(child), false) {
+            let _ = kill(child, Signal::SIGKILL);
+            return Err(err);
+        }
+        w_ack.write(SyncMessage::CgroupJoined)?;
+        let pid = match r_ipc.read()? {
+            SyncMessage::ChildPid(pid) => pid,
+            msg => bail!("unexpected message {:?}, want child pid", msg),
+        };
+        r_ipc.close()?;
+        if let Some(pid_file) = &self.pid_file {
+            std::fs::write(pid_file, pid.to_string())?;
+        }
+        if let Some(pty) = &pty {
+            tty::proxy(pty.master)?;
+        }
+        exit_code(waitpid(child, None)?)
+    }
+}
+
+//中间进程: 加入容器的namespace后fork出真正执行的进程, 并以它的退出码退出
+fn exec_process(
+    process: &Process,
+    target: Pid,
+    detach: bool,
+    w: &Writer<SyncMessage>,
+    r: &Reader<SyncMessage>,
+    pty: Option<&OpenptyResult>,
+) -> Result<()> {
+    match r.read()? {
+        SyncMessage::CgroupJoined => {}
+        msg => bail!("unexpected message {:?}, want cgroup joined", msg),
+    }
+    namespace::join_process(target)?;
+    let pid = fork_child(|| {
+        if let Some(pty) = pty {
+            close(pty.master)?;
+            tty::setup_slave(pty.slave)?;
+        }
+        do_exec(process)
+    })?;
+    w.write(SyncMessage::ChildPid(pid.as_raw()))?;
+    if detach {
+        return Ok(());
+    }
+    // 关闭pty, 进程退出后父进程才能读到EOF
+    if let Some(pty) = pty {
+        close(pty.master)?;
+        close(pty.slave)?;
+    }
+    std::process::exit(exit_code(waitpid(pid, None)?)?);
+}
+
+fn exit_code(status: WaitStatus) -> Result<i32> {
+    match status {
+        WaitStatus::Exited(_, code) => Ok(code),
+        WaitStatus::Signaled(_, signal, _) => Ok(128 + signal as i32),
+        status => bail!("unexpected wait status {:?}", status),
+    }
 }
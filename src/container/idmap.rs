@@ -0,0 +1,96 @@
+use crate::oci::oci::LinuxIdMapping;
+use crate::utils::fs;
+use anyhow::{bail, Context, Result};
+use nix::unistd::{geteuid, Pid};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+const NEWUIDMAP: &str = "newuidmap";
+const NEWGIDMAP: &str = "newgidmap";
+
+//父进程为创建了user namespace的子进程写入uid_map, gid_map和setgroups
+pub fn write_mappings(
+    pid: Pid,
+    uid_mappings: &[LinuxIdMapping],
+    gid_mappings: &[LinuxIdMapping],
+) -> Result<()> {
+    if !uid_mappings.is_empty() {
+        write_mapping(pid, uid_mappings, "uid_map", NEWUIDMAP)?;
+    }
+    if !gid_mappings.is_empty() {
+        write_mapping(pid, gid_mappings, "gid_map", NEWGIDMAP)?;
+    }
+    Ok(())
+}
+
+//单段映射直接写/proc/<pid>/*_map, 多段映射优先使用newuidmap/newgidmap
+fn write_mapping(pid: Pid, mappings: &[LinuxIdMapping], file: &str, helper: &str) -> Result<()> {
+    if mappings.len() > 1 {
+        if let Some(helper) = find_helper(helper) {
+            return run_helper(pid, mappings, &helper);
+        }
+    }
+    let proc_dir = PathBuf::from(format!("/proc/{}", pid));
+    // 非特权用户写gid_map之前必须禁止setgroups
+    if file == "gid_map" && !geteuid().is_root() {
+        std::fs::write(proc_dir.join("setgroups"), "deny")
+            .with_context(|| format!("failed to write setgroups for {}", pid))?;
+    }
+    std::fs::write(proc_dir.join(file), format_mappings(mappings))
+        .with_context(|| format!("failed to write {} for {}", file, pid))?;
+    Ok(())
+}
+
+fn find_helper(helper: &str) -> Option<PathBuf> {
+    let path_env = std::env::var("PATH").ok()?;
+    fs::find_executable(helper, &path_env).ok()
+}
+
+fn run_helper(pid: Pid, mappings: &[LinuxIdMapping], helper: &Path) -> Result<()> {
+    let mut args = vec![pid.to_string()];
+    for m in mappings {
+        args.push(m.container_id.to_string());
+        args.push(m.host_id.to_string());
+        args.push(m.size.to_string());
+    }
+    let output = Command::new(helper)
+        .args(&args)
+        .output()
+        .with_context(|| format!("failed to run {:?}", helper))?;
+    if !output.status.success() {
+        bail!(
+            "{:?} failed: {}",
+            helper,
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    Ok(())
+}
+
+fn format_mappings(mappings: &[LinuxIdMapping]) -> String {
+    mappings
+        .iter()
+        .map(|m| format!("{} {} {}\n", m.container_id, m.host_id, m.size))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    fn test_format_mappings() {
+        let mappings = vec![
+            LinuxIdMapping {
+                container_id: 0,
+                host_id: 1000,
+                size: 1,
+            },
+            LinuxIdMapping {
+                container_id: 1,
+                host_id: 100000,
+                size: 65536,
+            },
+        ];
+        assert_eq!(format_mappings(&mappings), "0 1000 1\n1 100000 65536\n");
+    }
+}
@@ -1,2 +1,10 @@
+#[allow(clippy::module_inception)]
 pub mod container;
+pub mod events;
+pub mod exec;
+pub mod idmap;
+pub mod mount;
+pub mod namespace;
+pub mod rootless;
+pub mod security;
 pub mod state;
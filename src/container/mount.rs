@@ -0,0 +1,386 @@
+use crate::oci::oci::Mount;
+use crate::utils::fs;
+use anyhow::{anyhow, bail, Context, Result};
+use nix::mount::{mount, MsFlags};
+use nix::sys::statfs::{statfs, CGROUP2_SUPER_MAGIC};
+use nix::sys::statvfs::{statvfs, FsFlags};
+use std::ffi::OsString;
+use std::os::unix::fs::symlink;
+use std::path::{Component, Path, PathBuf};
+
+const HOST_CGROUP_ROOT: &str = "/sys/fs/cgroup";
+//与linux的MAXSYMLINKS相同
+const MAX_SYMLINKS: usize = 40;
+
+//mount选项解析后的结果: flags用于mount, propagation需要单独mount一次, 其余选项作为data传给文件系统
+#[derive(Debug, PartialEq)]
+pub struct MountOptions {
+    pub flags: MsFlags,
+    pub propagation: MsFlags,
+    pub data: String,
+}
+
+pub fn parse_options(options: &[String]) -> MountOptions {
+    let mut flags = MsFlags::empty();
+    let mut propagation = MsFlags::empty();
+    let mut data: Vec<&str> = Vec::new();
+    for option in options {
+        // (是否清除flag, flag)
+        let flag = match option.as_str() {
+            "defaults" => Some((false, MsFlags::empty())),
+            "ro" => Some((false, MsFlags::MS_RDONLY)),
+            "rw" => Some((true, MsFlags::MS_RDONLY)),
+            "suid" => Some((true, MsFlags::MS_NOSUID)),
+            "nosuid" => Some((false, MsFlags::MS_NOSUID)),
+            "dev" => Some((true, MsFlags::MS_NODEV)),
+            "nodev" => Some((false, MsFlags::MS_NODEV)),
+            "exec" => Some((true, MsFlags::MS_NOEXEC)),
+            "noexec" => Some((false, MsFlags::MS_NOEXEC)),
+            "sync" => Some((false, MsFlags::MS_SYNCHRONOUS)),
+            "async" => Some((true, MsFlags::MS_SYNCHRONOUS)),
+            "dirsync" => Some((false, MsFlags::MS_DIRSYNC)),
+            "remount" => Some((false, MsFlags::MS_REMOUNT)),
+            "mand" => Some((false, MsFlags::MS_MANDLOCK)),
+            "nomand" => Some((true, MsFlags::MS_MANDLOCK)),
+            "atime" => Some((true, MsFlags::MS_NOATIME)),
+            "noatime" => Some((false, MsFlags::MS_NOATIME)),
+            "diratime" => Some((true, MsFlags::MS_NODIRATIME)),
+            "nodiratime" => Some((false, MsFlags::MS_NODIRATIME)),
+            "relatime" => Some((false, MsFlags::MS_RELATIME)),
+            "norelatime" => Some((true, MsFlags::MS_RELATIME)),
+            "strictatime" => Some((false, MsFlags::MS_STRICTATIME)),
+            "nostrictatime" => Some((true, MsFlags::MS_STRICTATIME)),
+            "bind" => Some((false, MsFlags::MS_BIND)),
+            "rbind" => Some((false, MsFlags::MS_BIND | MsFlags::MS_REC)),
+            _ => None,
+        };
+        if let Some((clear, flag)) = flag {
+            if clear {
+                flags.remove(flag);
+            } else {
+                flags.insert(flag);
+            }
+            continue;
+        }
+        let p = match option.as_str() {
+            "private" => MsFlags::MS_PRIVATE,
+            "rprivate" => MsFlags::MS_PRIVATE | MsFlags::MS_REC,
+            "shared" => MsFlags::MS_SHARED,
+            "rshared" => MsFlags::MS_SHARED | MsFlags::MS_REC,
+            "slave" => MsFlags::MS_SLAVE,
+            "rslave" => MsFlags::MS_SLAVE | MsFlags::MS_REC,
+            "unbindable" => MsFlags::MS_UNBINDABLE,
+            "runbindable" => MsFlags::MS_UNBINDABLE | MsFlags::MS_REC,
+            _ => {
+                data.push(option);
+                continue;
+            }
+        };
+        propagation.insert(p);
+    }
+    MountOptions {
+        flags,
+        propagation,
+        data: data.join(","),
+    }
+}
+
+//在pivot_root之前把spec中的mount挂载到rootfs中, 相对路径的bind source相对于bundle目录;
+//cgroup_dirs是容器在宿主机各个cgroup层级中的目录, 见cgroups::own_hierarchy_dirs
+pub fn mount_to_rootfs(
+    m: &Mount,
+    rootfs: &Path,
+    bundle: &Path,
+    cgroup_dirs: &[(PathBuf, PathBuf)],
+) -> Result<()> {
+    let opts = parse_options(m.options.as_deref().unwrap_or_default());
+    let dest = secure_join(rootfs, &m.destination)?;
+    if m.typ.as_deref() == Some("cgroup") {
+        // 只把容器所在的cgroup目录bind进来, 不暴露宿主机的整个cgroup层级
+        if statfs(HOST_CGROUP_ROOT)?.filesystem_type() == CGROUP2_SUPER_MAGIC {
+            mount_cgroup_v2(&dest, &opts, cgroup_dirs)
+        } else {
+            mount_cgroup_v1(&dest, &opts, cgroup_dirs)
+        }
+        .with_context(|| format!("failed to mount cgroup to {:?}", m.destination))?;
+        return set_propagation(&dest, &opts);
+    }
+    let flags = opts.flags;
+    let is_bind = flags.contains(MsFlags::MS_BIND);
+    let source = match &m.source {
+        Some(source) if is_bind && source.is_relative() => Some(bundle.join(source)),
+        source => source.clone(),
+    };
+    create_destination(&dest, source.as_deref(), is_bind)?;
+
+    let data = if opts.data.is_empty() {
+        None
+    } else {
+        Some(opts.data.as_str())
+    };
+    mount(source.as_deref(), &dest, m.typ.as_deref(), flags, data)
+        .with_context(|| format!("failed to mount {:?} to {:?}", m.source, m.destination))?;
+    // bind mount会忽略ro等flag, 需要remount一次
+    let remount_flags = flags & !(MsFlags::MS_BIND | MsFlags::MS_REC | MsFlags::MS_REMOUNT);
+    if is_bind && !remount_flags.is_empty() {
+        remount(&dest, remount_flags)?;
+    }
+    set_propagation(&dest, &opts)
+}
+
+fn set_propagation(dest: &Path, opts: &MountOptions) -> Result<()> {
+    if !opts.propagation.is_empty() {
+        mount::<Path, Path, str, str>(None, dest, None, opts.propagation, None)
+            .with_context(|| format!("failed to set propagation of {:?}", dest))?;
+    }
+    Ok(())
+}
+
+//v2时把容器在cgroup2中的目录bind到dest, 再按照mount的flag remount
+fn mount_cgroup_v2(
+    dest: &Path,
+    opts: &MountOptions,
+    cgroup_dirs: &[(PathBuf, PathBuf)],
+) -> Result<()> {
+    let dir = cgroup_dirs
+        .iter()
+        .find(|(mount_point, _)| mount_point == Path::new(HOST_CGROUP_ROOT))
+        .map(|(_, dir)| dir)
+        .ok_or_else(|| anyhow!("failed to find the cgroup of the container"))?;
+    let flags = opts.flags & !(MsFlags::MS_BIND | MsFlags::MS_REC | MsFlags::MS_REMOUNT);
+    fs::create_dir_all(dest)?;
+    mount::<Path, Path, str, str>(Some(dir), dest, None, MsFlags::MS_BIND, None)
+        .with_context(|| format!("failed to bind {:?}", dir))?;
+    if !flags.is_empty() {
+        remount(dest, flags)?;
+    }
+    Ok(())
+}
+
+//v1和hybrid时在tmpfs中为每个层级创建目录, 只bind容器自己的cgroup目录,
+//并分别remount, 只读时宿主机上的其他cgroup不会暴露或被修改
+fn mount_cgroup_v1(
+    dest: &Path,
+    opts: &MountOptions,
+    cgroup_dirs: &[(PathBuf, PathBuf)],
+) -> Result<()> {
+    let flags = opts.flags & !(MsFlags::MS_BIND | MsFlags::MS_REC | MsFlags::MS_REMOUNT);
+    fs::create_dir_all(dest)?;
+    mount(
+        Some("tmpfs"),
+        dest,
+        Some("tmpfs"),
+        flags & !MsFlags::MS_RDONLY,
+        Some("mode=755"),
+    )?;
+    for (mount_point, dir) in cgroup_dirs {
+        let name = match mount_point.file_name() {
+            Some(name) => name,
+            None => continue,
+        };
+        let sub = dest.join(name);
+        fs::create_dir_all(&sub)?;
+        mount::<Path, Path, str, str>(Some(dir), &sub, None, MsFlags::MS_BIND, None)
+            .with_context(|| format!("failed to bind {:?}", dir))?;
+        remount(&sub, flags)?;
+        // cpu,cpuacct这样合并挂载的层级, 为其中每个controller创建符号链接
+        let name = name.to_string_lossy();
+        if name.contains(',') {
+            for controller in name.split(',') {
+                symlink(name.as_ref(), dest.join(controller))?;
+            }
+        }
+    }
+    if flags.contains(MsFlags::MS_RDONLY) {
+        remount(dest, flags)?;
+    }
+    Ok(())
+}
+
+//遮盖rootfs中的路径: 目录挂载只读的tmpfs, 文件bind /dev/null, 不存在的路径忽略
+pub fn mask_path(rootfs: &Path, path: &Path) -> Result<()> {
+    let dest = secure_join(rootfs, path)?;
+    let meta = match std::fs::metadata(&dest) {
+        Ok(meta) => meta,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(()),
+        Err(err) => return Err(err).with_context(|| format!("failed to stat {:?}", path)),
+    };
+    let ret = if meta.is_dir() {
+        mount::<str, Path, str, str>(
+            Some("tmpfs"),
+            &dest,
+            Some("tmpfs"),
+            MsFlags::MS_RDONLY,
+            None,
+        )
+    } else {
+        mount::<str, Path, str, str>(Some("/dev/null"), &dest, None, MsFlags::MS_BIND, None)
+    };
+    ret.with_context(|| format!("failed to mask {:?}", path))
+}
+
+//把rootfs中的路径bind到自身后remount为只读, 不存在的路径忽略
+pub fn readonly_path(rootfs: &Path, path: &Path) -> Result<()> {
+    let dest = secure_join(rootfs, path)?;
+    if !dest.exists() {
+        return Ok(());
+    }
+    mount::<Path, Path, str, str>(
+        Some(&dest),
+        &dest,
+        None,
+        MsFlags::MS_BIND | MsFlags::MS_REC,
+        None,
+    )
+    .with_context(|| format!("failed to bind {:?}", path))?;
+    remount_readonly(&dest)
+}
+
+//remount已经挂载的目录, 需要保留原有的nosuid, nodev等flag,
+//否则在user namespace中这些被锁定的flag会导致remount失败
+fn remount(path: &Path, flags: MsFlags) -> Result<()> {
+    let fs_flags = statvfs(path)?.flags();
+    let mut flags = flags | MsFlags::MS_BIND | MsFlags::MS_REMOUNT;
+    for (fs_flag, flag) in [
+        (FsFlags::ST_NOSUID, MsFlags::MS_NOSUID),
+        (FsFlags::ST_NODEV, MsFlags::MS_NODEV),
+        (FsFlags::ST_NOEXEC, MsFlags::MS_NOEXEC),
+        (FsFlags::ST_NOATIME, MsFlags::MS_NOATIME),
+        (FsFlags::ST_NODIRATIME, MsFlags::MS_NODIRATIME),
+        (FsFlags::ST_RELATIME, MsFlags::MS_RELATIME),
+    ] {
+        if fs_flags.contains(fs_flag) {
+            flags.insert(flag);
+        }
+    }
+    mount::<Path, Path, str, str>(None, path, None, flags, None)
+        .with_context(|| format!("failed to remount {:?}", path))?;
+    Ok(())
+}
+
+//把已经挂载的目录remount为只读
+pub fn remount_readonly(path: &Path) -> Result<()> {
+    remount(path, MsFlags::MS_RDONLY)
+}
+
+//把容器中的路径解析为rootfs下的路径: 逐个component解析, 符号链接按照容器中的路径解析,
+//..和符号链接都不能离开rootfs; 不存在的部分不解析, 之后会被创建为普通的文件或目录
+pub fn secure_join(rootfs: &Path, path: &Path) -> Result<PathBuf> {
+    // 待解析的component, 倒序保存, 符号链接的目标会放到最前面
+    let mut pending: Vec<OsString> = Vec::new();
+    push_components(&mut pending, path);
+    let mut resolved = PathBuf::new();
+    let mut links = 0;
+    while let Some(name) = pending.pop() {
+        if name == ".." {
+            if !resolved.pop() {
+                bail!("{:?} is outside of rootfs {:?}", path, rootfs);
+            }
+            continue;
+        }
+        let next = resolved.join(&name);
+        let full = rootfs.join(&next);
+        match std::fs::symlink_metadata(&full) {
+            Ok(meta) if meta.file_type().is_symlink() => {
+                links += 1;
+                if links > MAX_SYMLINKS {
+                    bail!("too many levels of symbolic links in {:?}", path);
+                }
+                let target = std::fs::read_link(&full)?;
+                // 绝对路径的链接相对于rootfs
+                if target.is_absolute() {
+                    resolved = PathBuf::new();
+                }
+                push_components(&mut pending, &target);
+            }
+            _ => resolved = next,
+        }
+    }
+    Ok(rootfs.join(resolved))
+}
+
+fn push_components(pending: &mut Vec<OsString>, path: &Path) {
+    for component in path.components().rev() {
+        match component {
+            Component::Normal(name) => pending.push(name.to_owned()),
+            Component::ParentDir => pending.push(OsString::from("..")),
+            Component::RootDir | Component::CurDir | Component::Prefix(_) => {}
+        }
+    }
+}
+
+//bind mount文件时需要创建同名文件, 其余情况创建目录
+fn create_destination(dest: &Path, source: Option<&Path>, is_bind: bool) -> Result<()> {
+    if dest.exists() {
+        return Ok(());
+    }
+    let is_file = is_bind && source.map(|s| s.is_file()).unwrap_or(false);
+    if is_file {
+        if let Some(parent) = dest.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        std::fs::File::create(dest).with_context(|| format!("failed to create {:?}", dest))?;
+    } else {
+        fs::create_dir_all(dest)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    fn test_parse_options() {
+        let options: Vec<String> = [
+            "nosuid",
+            "strictatime",
+            "mode=755",
+            "size=65536k",
+            "rprivate",
+        ]
+        .iter()
+        .map(|s| s.to_string())
+        .collect();
+        let opts = parse_options(&options);
+        assert_eq!(opts.flags, MsFlags::MS_NOSUID | MsFlags::MS_STRICTATIME);
+        assert_eq!(opts.propagation, MsFlags::MS_PRIVATE | MsFlags::MS_REC);
+        assert_eq!(opts.data, "mode=755,size=65536k");
+
+        let options: Vec<String> = ["rbind", "ro", "rw", "nodev"]
+            .iter()
+            .map(|s| s.to_string())
+            .collect();
+        let opts = parse_options(&options);
+        assert_eq!(
+            opts.flags,
+            MsFlags::MS_BIND | MsFlags::MS_REC | MsFlags::MS_NODEV
+        );
+        assert!(opts.data.is_empty());
+    }
+
+    #[test]
+    fn test_secure_join() {
+        let rootfs = std::env::temp_dir().join(format!("smog-rootfs-{}", std::process::id()));
+        fs::create_dir_all(rootfs.join("usr/lib")).unwrap();
+        let link = |target: &str, name: &str| {
+            std::os::unix::fs::symlink(target, rootfs.join(name)).unwrap();
+        };
+        link("/usr/lib", "lib");
+        link("../../..", "usr/up");
+        link("usr/lib/../..", "root");
+        link("loop", "loop");
+
+        let join = |path: &str| secure_join(&rootfs, Path::new(path));
+        assert_eq!(
+            join("/lib/modules").unwrap(),
+            rootfs.join("usr/lib/modules")
+        );
+        assert_eq!(join("/usr/./lib/../bin").unwrap(), rootfs.join("usr/bin"));
+        assert_eq!(join("/root/dev").unwrap(), rootfs.join("dev"));
+        assert!(join("/../etc").is_err());
+        assert!(join("/usr/lib/../../../etc").is_err());
+        assert!(join("/usr/up/etc").is_err());
+        assert!(join("/loop/a").is_err());
+        std::fs::remove_dir_all(&rootfs).unwrap();
+    }
+}
@@ -0,0 +1,111 @@
+use crate::oci::oci::{Namespace, NamespaceType};
+use anyhow::{bail, Context, Result};
+use nix::errno::Errno;
+use nix::fcntl::{open, OFlag};
+use nix::sched::{setns, unshare, CloneFlags};
+use nix::sys::stat::Mode;
+use nix::unistd::{close, Pid};
+use std::os::unix::io::RawFd;
+
+// ioctl(fd, NS_GET_NSTYPE) 返回namespace文件对应的CLONE_NEW*
+const NS_GET_NSTYPE: libc::c_ulong = 0xb703;
+
+pub fn clone_flag(typ: &NamespaceType) -> CloneFlags {
+    match typ {
+        NamespaceType::Mount => CloneFlags::CLONE_NEWNS,
+        NamespaceType::Cgroup => CloneFlags::CLONE_NEWCGROUP,
+        NamespaceType::Uts => CloneFlags::CLONE_NEWUTS,
+        NamespaceType::Ipc => CloneFlags::CLONE_NEWIPC,
+        NamespaceType::User => CloneFlags::CLONE_NEWUSER,
+        NamespaceType::Pid => CloneFlags::CLONE_NEWPID,
+        NamespaceType::Network => CloneFlags::CLONE_NEWNET,
+    }
+}
+
+//namespace在/proc/<pid>/ns/下的文件名
+pub fn proc_name(typ: &NamespaceType) -> &'static str {
+    match typ {
+        NamespaceType::Mount => "mnt",
+        NamespaceType::Cgroup => "cgroup",
+        NamespaceType::Uts => "uts",
+        NamespaceType::Ipc => "ipc",
+        NamespaceType::User => "user",
+        NamespaceType::Pid => "pid",
+        NamespaceType::Network => "net",
+    }
+}
+
+pub fn find<'a>(namespaces: &'a [Namespace], typ: &NamespaceType) -> Option<&'a Namespace> {
+    namespaces.iter().find(|ns| &ns.typ == typ)
+}
+
+//有path时加入已有的namespace, 否则创建新的namespace
+pub fn enter(ns: &Namespace) -> Result<()> {
+    match &ns.path {
+        Some(path) => join(&ns.typ, path),
+        None => {
+            unshare(clone_flag(&ns.typ))
+                .with_context(|| format!("failed to unshare {:?} namespace", ns.typ))?;
+            Ok(())
+        }
+    }
+}
+
+//通过setns加入path指定的namespace, 比如/proc/<pid>/ns/net
+pub fn join(typ: &NamespaceType, path: &str) -> Result<()> {
+    let fd = open(path, OFlag::O_RDONLY | OFlag::O_CLOEXEC, Mode::empty())
+        .with_context(|| format!("failed to open namespace {}", path))?;
+    let ret = check_type(fd, typ)
+        .with_context(|| format!("invalid {:?} namespace {}", typ, path))
+        .and_then(|_| {
+            setns(fd, clone_flag(typ))
+                .with_context(|| format!("failed to join {:?} namespace {}", typ, path))
+        });
+    close(fd)?;
+    ret
+}
+
+//加入pid进程的所有namespace, user最先, mount最后, 已经在同一个namespace中的跳过
+//加入pid namespace只对之后fork出的子进程生效
+pub fn join_process(pid: Pid) -> Result<()> {
+    for typ in [
+        NamespaceType::User,
+        NamespaceType::Ipc,
+        NamespaceType::Uts,
+        NamespaceType::Network,
+        NamespaceType::Cgroup,
+        NamespaceType::Pid,
+        NamespaceType::Mount,
+    ] {
+        let path = format!("/proc/{}/ns/{}", pid, proc_name(&typ));
+        let own = format!("/proc/self/ns/{}", proc_name(&typ));
+        if std::fs::read_link(&path)? == std::fs::read_link(&own)? {
+            continue;
+        }
+        join(&typ, &path)?;
+    }
+    Ok(())
+}
+
+//校验namespace文件的类型和spec中声明的类型一致
+fn check_type(fd: RawFd, typ: &NamespaceType) -> Result<()> {
+    let ret = unsafe { libc::ioctl(fd, NS_GET_NSTYPE) };
+    let nstype = Errno::result(ret).context("not a namespace file")?;
+    if nstype != clone_flag(typ).bits() {
+        bail!("namespace type mismatch, want {:?}", typ);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    fn test_check_type() {
+        let fd = open("/proc/self/ns/uts", OFlag::O_RDONLY, Mode::empty()).unwrap();
+        assert!(check_type(fd, &NamespaceType::Uts).is_ok());
+        assert!(check_type(fd, &NamespaceType::Network).is_err());
+        close(fd).unwrap();
+        assert!(join(&NamespaceType::Network, "/proc/self/ns/uts").is_err());
+    }
+}
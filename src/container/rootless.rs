@@ -0,0 +1,141 @@
+use crate::oci::oci::{LinuxIdMapping, Mount, Namespace, NamespaceType, Spec};
+use anyhow::{Context, Result};
+use nix::unistd::{getegid, geteuid};
+use std::path::{Path, PathBuf};
+
+const RUNTIME_DIR_ENV: &str = "XDG_RUNTIME_DIR";
+
+//非root用户运行时使用rootless模式
+pub fn is_rootless() -> bool {
+    !geteuid().is_root()
+}
+
+//rootless模式下通常没有cgroup的写权限, 不要求cgroup必须生效时忽略错误并返回None,
+//提示写到stderr, 不影响run, exec等命令在stdout上的输出
+pub fn tolerate_cgroup_error<T>(ret: Result<T>, required: bool) -> Result<Option<T>> {
+    match ret {
+        Ok(v) => Ok(Some(v)),
+        Err(err) if is_rootless() && !required => {
+            eprintln!("ignore cgroup error in rootless mode: {}", err);
+            Ok(None)
+        }
+        Err(err) => Err(err),
+    }
+}
+
+//rootless模式下容器状态保存在$XDG_RUNTIME_DIR/smog
+pub fn root_path() -> PathBuf {
+    let runtime_dir = std::env::var_os(RUNTIME_DIR_ENV)
+        .map(PathBuf::from)
+        .unwrap_or_else(|| PathBuf::from(format!("/run/user/{}", geteuid())));
+    runtime_dir.join("smog")
+}
+
+//rootless模式下没有声明user namespace时, 自动创建一个并把当前用户映射为容器中的root
+pub fn adjust_spec(spec: &mut Spec) -> Result<()> {
+    let linux = spec.linux.as_mut().context("no linux in spec")?;
+    let namespaces = linux.namespaces.get_or_insert_with(Vec::new);
+    if !namespaces.iter().any(|ns| ns.typ == NamespaceType::User) {
+        namespaces.insert(
+            0,
+            Namespace {
+                typ: NamespaceType::User,
+                path: None,
+            },
+        );
+    }
+    if linux.uid_mappings.is_none() {
+        linux.uid_mappings = Some(vec![LinuxIdMapping {
+            container_id: 0,
+            host_id: geteuid().as_raw(),
+            size: 1,
+        }]);
+    }
+    if linux.gid_mappings.is_none() {
+        linux.gid_mappings = Some(vec![LinuxIdMapping {
+            container_id: 0,
+            host_id: getegid().as_raw(),
+            size: 1,
+        }]);
+    }
+    Ok(())
+}
+
+//smog spec --rootless生成的配置: 非root用户无法为新的network namespace配置网络, 因此共享宿主机网络,
+//不属于自己的network namespace中不能挂载sysfs, 改为bind宿主机的/sys, cgroup也无法挂载;
+//容器中只映射了当前用户, mount中的uid=和gid=选项也需要去掉
+pub fn rootless_spec() -> Result<Spec> {
+    let mut spec = Spec::example();
+    let linux = spec.linux.as_mut().context("no linux in spec")?;
+    if let Some(namespaces) = linux.namespaces.as_mut() {
+        namespaces.retain(|ns| ns.typ != NamespaceType::Network);
+    }
+    linux.resources = None;
+    adjust_spec(&mut spec)?;
+    let mounts = spec.mounts.take().unwrap_or_default();
+    spec.mounts = Some(
+        mounts
+            .into_iter()
+            .filter(|m| m.typ.as_deref() != Some("cgroup"))
+            .map(|mut m| {
+                if m.destination == Path::new("/sys") {
+                    return Mount {
+                        destination: m.destination,
+                        typ: Some("none".to_owned()),
+                        source: Some(PathBuf::from("/sys")),
+                        options: Some(
+                            ["rbind", "nosuid", "noexec", "nodev", "ro"]
+                                .iter()
+                                .map(|o| o.to_string())
+                                .collect(),
+                        ),
+                    };
+                }
+                if let Some(options) = m.options.as_mut() {
+                    options.retain(|o| !o.starts_with("uid=") && !o.starts_with("gid="));
+                }
+                m
+            })
+            .collect(),
+    );
+    Ok(spec)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    fn test_adjust_spec() {
+        let s = r#"{"ociVersion":"1.0.2","linux":{"namespaces":[{"type":"pid"}]}}"#;
+        let mut spec: Spec = serde_json::from_str(s).unwrap();
+        adjust_spec(&mut spec).unwrap();
+        let linux = spec.linux.unwrap();
+        let namespaces = linux.namespaces.unwrap();
+        assert_eq!(namespaces[0].typ, NamespaceType::User);
+        assert_eq!(namespaces.len(), 2);
+        let uid_mappings = linux.uid_mappings.unwrap();
+        assert_eq!(uid_mappings[0].host_id, geteuid().as_raw());
+        assert_eq!(uid_mappings[0].container_id, 0);
+    }
+
+    #[test]
+    fn test_rootless_spec() {
+        let spec = rootless_spec().unwrap();
+        let linux = spec.linux.unwrap();
+        let namespaces = linux.namespaces.unwrap();
+        assert_eq!(namespaces[0].typ, NamespaceType::User);
+        assert!(!namespaces.iter().any(|ns| ns.typ == NamespaceType::Network));
+        assert!(linux.gid_mappings.is_some());
+        let mounts = spec.mounts.unwrap();
+        assert!(!mounts.iter().any(|m| m.typ.as_deref() == Some("cgroup")));
+        let devpts = mounts
+            .iter()
+            .find(|m| m.destination == Path::new("/dev/pts"))
+            .unwrap();
+        assert!(!devpts
+            .options
+            .as_ref()
+            .unwrap()
+            .contains(&"gid=5".to_owned()));
+    }
+}
@@ -0,0 +1,182 @@
+use crate::oci::oci::{LinuxCapabilities, PosixRlimit};
+use anyhow::{bail, Context, Result};
+use nix::errno::Errno;
+use nix::sys::resource::{setrlimit, Resource};
+
+//capability的名称, 下标即capability的编号
+const CAPABILITIES: &[&str] = &[
+    "CAP_CHOWN",
+    "CAP_DAC_OVERRIDE",
+    "CAP_DAC_READ_SEARCH",
+    "CAP_FOWNER",
+    "CAP_FSETID",
+    "CAP_KILL",
+    "CAP_SETGID",
+    "CAP_SETUID",
+    "CAP_SETPCAP",
+    "CAP_LINUX_IMMUTABLE",
+    "CAP_NET_BIND_SERVICE",
+    "CAP_NET_BROADCAST",
+    "CAP_NET_ADMIN",
+    "CAP_NET_RAW",
+    "CAP_IPC_LOCK",
+    "CAP_IPC_OWNER",
+    "CAP_SYS_MODULE",
+    "CAP_SYS_RAWIO",
+    "CAP_SYS_CHROOT",
+    "CAP_SYS_PTRACE",
+    "CAP_SYS_PACCT",
+    "CAP_SYS_ADMIN",
+    "CAP_SYS_BOOT",
+    "CAP_SYS_NICE",
+    "CAP_SYS_RESOURCE",
+    "CAP_SYS_TIME",
+    "CAP_SYS_TTY_CONFIG",
+    "CAP_MKNOD",
+    "CAP_LEASE",
+    "CAP_AUDIT_WRITE",
+    "CAP_AUDIT_CONTROL",
+    "CAP_SETFCAP",
+    "CAP_MAC_OVERRIDE",
+    "CAP_MAC_ADMIN",
+    "CAP_SYSLOG",
+    "CAP_WAKE_ALARM",
+    "CAP_BLOCK_SUSPEND",
+    "CAP_AUDIT_READ",
+    "CAP_PERFMON",
+    "CAP_BPF",
+    "CAP_CHECKPOINT_RESTORE",
+];
+const CAPABILITY_VERSION_3: u32 = 0x20080522;
+
+#[repr(C)]
+struct CapUserHeader {
+    version: u32,
+    pid: i32,
+}
+
+#[repr(C)]
+#[derive(Default, Clone, Copy)]
+struct CapUserData {
+    effective: u32,
+    permitted: u32,
+    inheritable: u32,
+}
+
+//设置process中的rlimits, 需要在切换用户之前设置, 否则无法提高hard limit
+pub fn set_rlimits(rlimits: &[PosixRlimit]) -> Result<()> {
+    for rlimit in rlimits {
+        let resource = match rlimit.typ.as_str() {
+            "RLIMIT_AS" => Resource::RLIMIT_AS,
+            "RLIMIT_CORE" => Resource::RLIMIT_CORE,
+            "RLIMIT_CPU" => Resource::RLIMIT_CPU,
+            "RLIMIT_DATA" => Resource::RLIMIT_DATA,
+            "RLIMIT_FSIZE" => Resource::RLIMIT_FSIZE,
+            "RLIMIT_LOCKS" => Resource::RLIMIT_LOCKS,
+            "RLIMIT_MEMLOCK" => Resource::RLIMIT_MEMLOCK,
+            "RLIMIT_MSGQUEUE" => Resource::RLIMIT_MSGQUEUE,
+            "RLIMIT_NICE" => Resource::RLIMIT_NICE,
+            "RLIMIT_NOFILE" => Resource::RLIMIT_NOFILE,
+            "RLIMIT_NPROC" => Resource::RLIMIT_NPROC,
+            "RLIMIT_RSS" => Resource::RLIMIT_RSS,
+            "RLIMIT_RTPRIO" => Resource::RLIMIT_RTPRIO,
+            "RLIMIT_RTTIME" => Resource::RLIMIT_RTTIME,
+            "RLIMIT_SIGPENDING" => Resource::RLIMIT_SIGPENDING,
+            "RLIMIT_STACK" => Resource::RLIMIT_STACK,
+            typ => bail!("unknown rlimit type {}", typ),
+        };
+        setrlimit(resource, Some(rlimit.soft), Some(rlimit.hard))
+            .with_context(|| format!("failed to set {}", rlimit.typ))?;
+    }
+    Ok(())
+}
+
+//从bounding set中去掉没有列出的capability, 需要CAP_SETPCAP, 因此在capset之前调用
+pub fn drop_bounding_set(capabilities: &LinuxCapabilities) -> Result<()> {
+    let bounding = to_mask(capabilities.bounding.as_deref())?;
+    for (cap, name) in CAPABILITIES.iter().enumerate().take(last_capability() + 1) {
+        if bounding & (1 << cap) == 0 {
+            prctl(libc::PR_CAPBSET_DROP, cap as libc::c_ulong, 0)
+                .with_context(|| format!("failed to drop {} from bounding set", name))?;
+        }
+    }
+    Ok(())
+}
+
+//切换到非root用户时默认会清空permitted, 切换前打开keep caps, 之后再由set_capabilities设置
+pub fn keep_capabilities(keep: bool) -> Result<()> {
+    prctl(libc::PR_SET_KEEPCAPS, keep as libc::c_ulong, 0).context("failed to set keep caps")
+}
+
+//设置effective, permitted, inheritable和ambient, 没有列出的capability都会被去掉
+pub fn set_capabilities(capabilities: &LinuxCapabilities) -> Result<()> {
+    let supported = (1u64 << (last_capability() + 1)) - 1;
+    let effective = to_mask(capabilities.effective.as_deref())? & supported;
+    let permitted = to_mask(capabilities.permitted.as_deref())? & supported;
+    let inheritable = to_mask(capabilities.inheritable.as_deref())? & supported;
+    let header = CapUserHeader {
+        version: CAPABILITY_VERSION_3,
+        pid: 0,
+    };
+    let mut data = [CapUserData::default(); 2];
+    for (i, d) in data.iter_mut().enumerate() {
+        d.effective = (effective >> (32 * i)) as u32;
+        d.permitted = (permitted >> (32 * i)) as u32;
+        d.inheritable = (inheritable >> (32 * i)) as u32;
+    }
+    let ret = unsafe { libc::syscall(libc::SYS_capset, &header, data.as_ptr()) };
+    Errno::result(ret).context("failed to set capabilities")?;
+    let ambient = to_mask(capabilities.ambient.as_deref())? & supported;
+    for (cap, name) in CAPABILITIES.iter().enumerate() {
+        if ambient & (1 << cap) != 0 {
+            prctl(
+                libc::PR_CAP_AMBIENT,
+                libc::PR_CAP_AMBIENT_RAISE as libc::c_ulong,
+                cap as libc::c_ulong,
+            )
+            .with_context(|| format!("failed to raise ambient {}", name))?;
+        }
+    }
+    Ok(())
+}
+
+pub fn set_no_new_privileges() -> Result<()> {
+    prctl(libc::PR_SET_NO_NEW_PRIVS, 1, 0).context("failed to set no new privileges")
+}
+
+//没有列出的capability表示不授予
+fn to_mask(names: Option<&[String]>) -> Result<u64> {
+    let mut mask = 0;
+    for name in names.unwrap_or_default() {
+        match CAPABILITIES.iter().position(|c| c == name) {
+            Some(cap) => mask |= 1 << cap,
+            None => bail!("unknown capability {}", name),
+        }
+    }
+    Ok(mask)
+}
+
+//内核支持的最大capability编号, 旧内核不认识的capability在PR_CAPBSET_READ时返回EINVAL
+fn last_capability() -> usize {
+    (1..CAPABILITIES.len())
+        .take_while(|cap| prctl(libc::PR_CAPBSET_READ, *cap as libc::c_ulong, 0).is_ok())
+        .last()
+        .unwrap_or(0)
+}
+
+fn prctl(option: libc::c_int, arg2: libc::c_ulong, arg3: libc::c_ulong) -> nix::Result<()> {
+    let ret = unsafe { libc::prctl(option, arg2, arg3, 0, 0) };
+    Errno::result(ret).map(drop)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    fn test_to_mask() {
+        let names = vec!["CAP_CHOWN".to_owned(), "CAP_KILL".to_owned()];
+        assert_eq!(to_mask(Some(&names)).unwrap(), 0b100001);
+        assert_eq!(to_mask(None).unwrap(), 0);
+        assert!(to_mask(Some(&["CAP_UNKNOWN".to_owned()])).is_err());
+    }
+}
@@ -1,8 +1,12 @@
+use crate::oci::oci::LinuxResources;
 use anyhow::Result;
+use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
+use std::fmt::Display;
 use std::fs::{self, File};
 use std::path::PathBuf;
-use std::{collections::HashMap, io::Write, path::Path};
+use std::{collections::HashMap, path::Path};
 
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
@@ -14,6 +18,19 @@ pub enum Status {
     Paused,
 }
 
+impl Display for Status {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let print = match *self {
+            Self::Creating => "creating",
+            Self::Created => "created",
+            Self::Running => "running",
+            Self::Stopped => "stopped",
+            Self::Paused => "paused",
+        };
+        write!(f, "{}", print)
+    }
+}
+
 #[derive(Serialize, Deserialize, Debug, Clone)]
 #[serde(rename_all = "camelCase")]
 pub struct State {
@@ -21,8 +38,39 @@ pub struct State {
     pub id: String,
     pub status: Status,
     pub pid: i32,
+    //init进程的启动时间(开机后的clock tick), 用于识别被复用的pid
+    #[serde(default)]
+    pub pid_start_time: Option<u64>,
     pub bundle: PathBuf,
     pub annotations: Option<HashMap<String, String>>,
+    #[serde(default)]
+    pub created: Option<DateTime<Utc>>,
+    //当前生效的资源限制, update之后与spec中的不同
+    #[serde(default)]
+    pub resources: Option<LinuxResources>,
+    //创建时确定的cgroup路径, 相对路径依赖创建时所在的cgroup, 之后不能再从spec中计算
+    #[serde(default)]
+    pub cgroups_path: Option<PathBuf>,
+    //创建时新建的最上层cgroup目录, 删除时父目录只删除到这一层
+    #[serde(default)]
+    pub created_cgroups_path: Option<PathBuf>,
+    //cgroup由systemd管理, 此时cgroups_path为slice:prefix:name
+    #[serde(default)]
+    pub systemd_cgroup: bool,
+}
+
+//OCI规范中的状态, state命令只输出这些字段, 其余字段是smog内部使用的
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct OciState<'a> {
+    pub oci_version: &'a str,
+    pub id: &'a str,
+    pub status: &'a Status,
+    //停止之后pid已经没有意义, 不输出
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pid: Option<i32>,
+    pub bundle: &'a Path,
+    pub annotations: Cow<'a, HashMap<String, String>>,
 }
 
 impl State {
@@ -33,9 +81,33 @@ impl State {
             oci_version: Self::OCI_VERSION.to_string(),
             id: id.to_string(),
             status: Status::Creating,
-            pid: pid,
-            bundle: bundle,
+            pid,
+            pid_start_time: None,
+            bundle,
             annotations: Some(HashMap::default()),
+            created: Some(Utc::now()),
+            resources: None,
+            cgroups_path: None,
+            created_cgroups_path: None,
+            systemd_cgroup: false,
+        }
+    }
+
+    pub fn oci_state(&self) -> OciState<'_> {
+        OciState {
+            oci_version: &self.oci_version,
+            id: &self.id,
+            status: &self.status,
+            pid: match self.status {
+                Status::Stopped => None,
+                _ => Some(self.pid),
+            },
+            bundle: &self.bundle,
+            annotations: self
+                .annotations
+                .as_ref()
+                .map(Cow::Borrowed)
+                .unwrap_or_default(),
         }
     }
 
@@ -46,6 +118,10 @@ impl State {
         Ok(state)
     }
 
+    pub fn exists(container_dir: &Path) -> bool {
+        Self::state_file_path(container_dir).exists()
+    }
+
     fn state_file_path(container_dir: &Path) -> PathBuf {
         container_dir.join(Self::STATE_FILE)
     }
@@ -62,3 +138,45 @@ impl State {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    fn test_oci_state() {
+        let mut state = State::new("abc", 1234, PathBuf::from("/bundle"));
+        state.status = Status::Running;
+        state.pid_start_time = Some(1);
+        state.cgroups_path = Some(PathBuf::from("smog/abc"));
+        let keys = |state: &State| -> Vec<String> {
+            let value = serde_json::to_value(state.oci_state()).unwrap();
+            let mut keys: Vec<String> = value.as_object().unwrap().keys().cloned().collect();
+            keys.sort();
+            keys
+        };
+        assert_eq!(
+            keys(&state),
+            ["annotations", "bundle", "id", "ociVersion", "pid", "status"]
+        );
+        let value = serde_json::to_value(state.oci_state()).unwrap();
+        assert_eq!(value["pid"], 1234);
+        assert_eq!(value["status"], "running");
+
+        state.status = Status::Stopped;
+        state.annotations = None;
+        assert_eq!(
+            keys(&state),
+            ["annotations", "bundle", "id", "ociVersion", "status"]
+        );
+    }
+
+    #[test]
+    fn test_load_old_state() {
+        // 只有基本字段的state.json, 新增的字段都取默认值
+        let s = r#"{"ociVersion":"1.0.2","id":"abc","status":"created","pid":1,"bundle":"/b","annotations":{}}"#;
+        let state: State = serde_json::from_str(s).unwrap();
+        assert_eq!(state.pid_start_time, None);
+        assert_eq!(state.cgroups_path, None);
+        assert!(!state.systemd_cgroup);
+    }
+}
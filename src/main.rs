@@ -1,17 +1,23 @@
 mod cgroups;
 mod cli;
-mod common;
 mod container;
 mod oci;
 mod opts;
 mod utils;
 use clap::Parser;
-use cli::{Create, Run, Start};
-use opts::{create, run, start};
+use cli::{
+    Create, Delete, Events, Exec, Kill, List, Pause, Ps, Resume, Run, Spec, Start, State, Update,
+};
+use opts::{
+    create, delete, events, exec, kill, list, pause, ps, resume, run, spec, start, state, update,
+};
 
 #[derive(Parser, Debug)]
 #[clap(about, version, author)]
 struct Opts {
+    /// Use systemd to manage the cgroups of created containers
+    #[clap(long, global = true)]
+    systemd_cgroup: bool,
     #[clap(subcommand)]
     subcmd: SubCommand,
 }
@@ -21,23 +27,64 @@ enum SubCommand {
     Create(Create),
     Start(Start),
     Run(Run),
-    Spec,
+    State(State),
+    Kill(Kill),
+    Delete(Delete),
+    List(List),
+    Exec(Exec),
+    Pause(Pause),
+    Resume(Resume),
+    Ps(Ps),
+    Events(Events),
+    Update(Update),
+    Spec(Spec),
 }
 
 fn main() {
     let opts = Opts::parse();
-    println!("Hello {:?}!", opts.subcmd);
     match opts.subcmd {
         SubCommand::Create(c) => {
-            println!("{:?}", c);
-            create(c).unwrap();
+            create(c, opts.systemd_cgroup).unwrap();
         }
         SubCommand::Start(s) => {
             start(s).unwrap();
         }
         SubCommand::Run(r) => {
-            run(r).unwrap();
+            run(r, opts.systemd_cgroup).unwrap();
+        }
+        SubCommand::State(s) => {
+            state(s).unwrap();
+        }
+        SubCommand::Kill(k) => {
+            kill(k).unwrap();
+        }
+        SubCommand::Delete(d) => {
+            delete(d).unwrap();
+        }
+        SubCommand::List(l) => {
+            list(l).unwrap();
+        }
+        SubCommand::Exec(e) => {
+            let code = exec(e).unwrap();
+            std::process::exit(code);
+        }
+        SubCommand::Pause(p) => {
+            pause(p).unwrap();
+        }
+        SubCommand::Resume(r) => {
+            resume(r).unwrap();
+        }
+        SubCommand::Ps(p) => {
+            ps(p).unwrap();
+        }
+        SubCommand::Events(e) => {
+            events(e).unwrap();
+        }
+        SubCommand::Update(u) => {
+            update(u).unwrap();
+        }
+        SubCommand::Spec(s) => {
+            spec(s).unwrap();
         }
-        SubCommand::Spec => {}
     }
 }
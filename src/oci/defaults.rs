@@ -0,0 +1,174 @@
+use super::oci::{
+    Linux, LinuxCapabilities, Mount, Namespace, NamespaceType, PosixRlimit, Process, Root, Spec,
+    User,
+};
+use std::path::PathBuf;
+
+const OCI_VERSION: &str = "1.0.2";
+const DEFAULT_CAPABILITIES: &[&str] = &["CAP_AUDIT_WRITE", "CAP_KILL", "CAP_NET_BIND_SERVICE"];
+const DEFAULT_MASKED_PATHS: &[&str] = &[
+    "/proc/acpi",
+    "/proc/asound",
+    "/proc/kcore",
+    "/proc/keys",
+    "/proc/latency_stats",
+    "/proc/timer_list",
+    "/proc/timer_stats",
+    "/proc/sched_debug",
+    "/sys/firmware",
+    "/proc/scsi",
+];
+const DEFAULT_READONLY_PATHS: &[&str] = &[
+    "/proc/bus",
+    "/proc/fs",
+    "/proc/irq",
+    "/proc/sys",
+    "/proc/sysrq-trigger",
+];
+
+//smog spec生成的示例配置, 与runc spec的输出保持一致
+impl Spec {
+    pub fn example() -> Self {
+        Self {
+            oci_version: OCI_VERSION.to_owned(),
+            process: Some(Process::example()),
+            root: Some(Root {
+                path: PathBuf::from("rootfs"),
+                readonly: true,
+            }),
+            hostname: Some("smog".to_owned()),
+            mounts: Some(default_mounts()),
+            linux: Some(Linux::example()),
+        }
+    }
+}
+
+impl Process {
+    fn example() -> Self {
+        let capabilities = Some(to_strings(DEFAULT_CAPABILITIES));
+        Self {
+            terminal: true,
+            user: User::default(),
+            args: Some(vec!["sh".to_owned()]),
+            env: Some(vec![
+                "PATH=/usr/local/sbin:/usr/local/bin:/usr/sbin:/usr/bin:/sbin:/bin".to_owned(),
+                "TERM=xterm".to_owned(),
+            ]),
+            cwd: PathBuf::from("/"),
+            capabilities: Some(LinuxCapabilities {
+                bounding: capabilities.clone(),
+                effective: capabilities.clone(),
+                inheritable: None,
+                permitted: capabilities,
+                ambient: None,
+            }),
+            rlimits: Some(vec![PosixRlimit {
+                typ: "RLIMIT_NOFILE".to_owned(),
+                hard: 1024,
+                soft: 1024,
+            }]),
+            no_new_privileges: Some(true),
+        }
+    }
+}
+
+impl Linux {
+    fn example() -> Self {
+        let namespaces = [
+            NamespaceType::Pid,
+            NamespaceType::Network,
+            NamespaceType::Ipc,
+            NamespaceType::Uts,
+            NamespaceType::Mount,
+        ]
+        .into_iter()
+        .map(|typ| Namespace { typ, path: None })
+        .collect();
+        Self {
+            namespaces: Some(namespaces),
+            uid_mappings: None,
+            gid_mappings: None,
+            resources: None,
+            cgroups_path: None,
+            masked_paths: Some(to_strings(DEFAULT_MASKED_PATHS)),
+            readonly_paths: Some(to_strings(DEFAULT_READONLY_PATHS)),
+        }
+    }
+}
+
+fn default_mounts() -> Vec<Mount> {
+    let mount = |destination: &str, typ: &str, source: &str, options: &[&str]| Mount {
+        destination: PathBuf::from(destination),
+        typ: Some(typ.to_owned()),
+        source: Some(PathBuf::from(source)),
+        options: if options.is_empty() {
+            None
+        } else {
+            Some(to_strings(options))
+        },
+    };
+    vec![
+        mount("/proc", "proc", "proc", &[]),
+        mount(
+            "/dev",
+            "tmpfs",
+            "tmpfs",
+            &["nosuid", "strictatime", "mode=755", "size=65536k"],
+        ),
+        mount(
+            "/dev/pts",
+            "devpts",
+            "devpts",
+            &[
+                "nosuid",
+                "noexec",
+                "newinstance",
+                "ptmxmode=0666",
+                "mode=0620",
+                "gid=5",
+            ],
+        ),
+        mount(
+            "/dev/shm",
+            "tmpfs",
+            "shm",
+            &["nosuid", "noexec", "nodev", "mode=1777", "size=65536k"],
+        ),
+        mount(
+            "/dev/mqueue",
+            "mqueue",
+            "mqueue",
+            &["nosuid", "noexec", "nodev"],
+        ),
+        mount(
+            "/sys",
+            "sysfs",
+            "sysfs",
+            &["nosuid", "noexec", "nodev", "ro"],
+        ),
+        mount(
+            "/sys/fs/cgroup",
+            "cgroup",
+            "cgroup",
+            &["nosuid", "noexec", "nodev", "relatime", "ro"],
+        ),
+    ]
+}
+
+fn to_strings(values: &[&str]) -> Vec<String> {
+    values.iter().map(|v| v.to_string()).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    fn test_example_spec() {
+        let json = serde_json::to_string(&Spec::example()).unwrap();
+        // 没有设置的可选字段不输出null
+        assert!(!json.contains("null"));
+        let spec: Spec = serde_json::from_str(&json).unwrap();
+        assert_eq!(spec.process.unwrap().args, Some(vec!["sh".to_owned()]));
+        assert_eq!(spec.mounts.unwrap().len(), 7);
+    }
+}
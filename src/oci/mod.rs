@@ -1,4 +1,6 @@
-use std::{borrow::Cow, io};
+use std::borrow::Cow;
+mod defaults;
+#[allow(clippy::module_inception)]
 pub mod oci;
 
 #[derive(Debug)]
@@ -16,3 +18,13 @@ where
         Cow::Owned(s) => OciError::Error(s),
     }
 }
+
+impl std::fmt::Display for OciError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            OciError::Error(s) => write!(f, "{}", s),
+        }
+    }
+}
+
+impl std::error::Error for OciError {}
@@ -6,11 +6,19 @@ use std::fs::File;
 use std::io::BufReader;
 use std::path::PathBuf;
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Default)]
 #[serde(rename_all = "camelCase")]
 pub struct Spec {
     pub oci_version: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub process: Option<Process>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub root: Option<Root>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub hostname: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub mounts: Option<Vec<Mount>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub linux: Option<Linux>,
 }
 
@@ -22,6 +30,73 @@ impl Spec {
     }
 }
 
+impl Process {
+    pub fn load(path: PathBuf) -> Result<Process> {
+        let file = File::open(path)?;
+        let reader = BufReader::new(file);
+        Ok(serde_json::from_reader(reader)?)
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct Process {
+    #[serde(default)]
+    pub terminal: bool,
+    #[serde(default)]
+    pub user: User,
+    pub args: Option<Vec<String>>,
+    pub env: Option<Vec<String>>,
+    pub cwd: PathBuf,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub capabilities: Option<LinuxCapabilities>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub rlimits: Option<Vec<PosixRlimit>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub no_new_privileges: Option<bool>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct User {
+    pub uid: u32,
+    pub gid: u32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub additional_gids: Option<Vec<u32>>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct LinuxCapabilities {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub bounding: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub effective: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub inheritable: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub permitted: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ambient: Option<Vec<String>>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct PosixRlimit {
+    #[serde(rename = "type")]
+    pub typ: String,
+    pub hard: u64,
+    pub soft: u64,
+}
+
+impl Process {
+    //在env中查找PATH
+    pub fn path_env(&self) -> Option<&str> {
+        self.env
+            .as_ref()?
+            .iter()
+            .find_map(|e| e.strip_prefix("PATH="))
+    }
+}
+
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct Root {
     pub path: PathBuf,
@@ -29,17 +104,103 @@ pub struct Root {
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Mount {
+    pub destination: PathBuf,
+    #[serde(rename = "type")]
+    pub typ: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub source: Option<PathBuf>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub options: Option<Vec<String>>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
 #[serde(rename_all = "camelCase")]
 pub struct Linux {
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub namespaces: Option<Vec<Namespace>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub uid_mappings: Option<Vec<LinuxIdMapping>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub gid_mappings: Option<Vec<LinuxIdMapping>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub resources: Option<LinuxResources>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cgroups_path: Option<PathBuf>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub masked_paths: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub readonly_paths: Option<Vec<String>>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct LinuxIdMapping {
+    #[serde(rename = "containerID")]
+    pub container_id: u32,
+    #[serde(rename = "hostID")]
+    pub host_id: u32,
+    pub size: u32,
 }
 
 #[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct LinuxResources {
+    pub devices: Option<Vec<LinuxDeviceCgroup>>,
     pub cpu: Option<LinuxCpu>,
     pub memory: Option<LinuxMemory>,
+    pub pids: Option<LinuxPids>,
+    #[serde(rename = "blockIO")]
+    pub block_io: Option<LinuxBlockIo>,
+    pub hugepage_limits: Option<Vec<LinuxHugepageLimit>>,
+    pub network: Option<LinuxNetwork>,
+}
+
+impl LinuxResources {
+    pub fn load(path: PathBuf) -> Result<LinuxResources> {
+        let file = File::open(path)?;
+        let reader = BufReader::new(file);
+        Ok(serde_json::from_reader(reader)?)
+    }
+
+    //是否设置了资源限制, 设备规则不算在内: 它只是收紧设备的访问权限, 不生效时容器仍然可以运行
+    pub fn has_limits(&self) -> bool {
+        self.cpu.is_some()
+            || self.memory.is_some()
+            || self.pids.is_some()
+            || self.block_io.is_some()
+            || self.hugepage_limits.as_ref().is_some_and(|h| !h.is_empty())
+    }
+
+    //用other中设置了的字段覆盖当前的值, 没有设置的字段保持不变
+    pub fn merge(&mut self, other: &LinuxResources) -> Result<()> {
+        let mut current = serde_json::to_value(&*self)?;
+        merge_value(&mut current, serde_json::to_value(other)?);
+        *self = serde_json::from_value(current)?;
+        Ok(())
+    }
+}
+
+fn merge_value(current: &mut serde_json::Value, other: serde_json::Value) {
+    match (current, other) {
+        (_, serde_json::Value::Null) => {}
+        (serde_json::Value::Object(current), serde_json::Value::Object(other)) => {
+            for (key, value) in other {
+                merge_value(current.entry(key).or_insert(serde_json::Value::Null), value);
+            }
+        }
+        (current, other) => *current = other,
+    }
+}
+
+//设备访问规则, 类型为a(全部), c或b, major和minor为空时匹配所有, access是rwm的组合
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LinuxDeviceCgroup {
+    pub allow: bool,
+    #[serde(rename = "type")]
+    pub typ: Option<String>,
+    pub major: Option<i64>,
+    pub minor: Option<i64>,
+    pub access: Option<String>,
 }
 
 #[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
@@ -57,17 +218,84 @@ pub struct LinuxCpu {
 #[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct LinuxMemory {
-    limit: Option<i64>,
-    reservation: Option<i64>,
-    swap: Option<i64>,
-    kernel: Option<i64>,
-    kernel_tcp: Option<i64>,
-    swappiness: Option<u64>,
-    disable_oom_killer: Option<bool>,
-    use_hierarchy: Option<bool>,
+    pub limit: Option<i64>,
+    pub reservation: Option<i64>,
+    pub swap: Option<i64>,
+    pub kernel: Option<i64>,
+    pub kernel_tcp: Option<i64>,
+    pub swappiness: Option<u64>,
+    pub disable_oom_killer: Option<bool>,
+    pub use_hierarchy: Option<bool>,
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LinuxPids {
+    pub limit: i64,
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LinuxBlockIo {
+    pub weight: Option<u16>,
+    pub leaf_weight: Option<u16>,
+    pub weight_device: Option<Vec<LinuxWeightDevice>>,
+    pub throttle_read_bps_device: Option<Vec<LinuxThrottleDevice>>,
+    pub throttle_write_bps_device: Option<Vec<LinuxThrottleDevice>>,
+    #[serde(rename = "throttleReadIOPSDevice")]
+    pub throttle_read_iops_device: Option<Vec<LinuxThrottleDevice>>,
+    #[serde(rename = "throttleWriteIOPSDevice")]
+    pub throttle_write_iops_device: Option<Vec<LinuxThrottleDevice>>,
+}
+
+//块设备, path不是OCI标准中的字段, 设置时从设备文件中获取major和minor
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LinuxBlockIoDevice {
+    #[serde(default)]
+    pub major: i64,
+    #[serde(default)]
+    pub minor: i64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub path: Option<PathBuf>,
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LinuxWeightDevice {
+    #[serde(flatten)]
+    pub device: LinuxBlockIoDevice,
+    pub weight: Option<u16>,
+    pub leaf_weight: Option<u16>,
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LinuxThrottleDevice {
+    #[serde(flatten)]
+    pub device: LinuxBlockIoDevice,
+    pub rate: u64,
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LinuxHugepageLimit {
+    //大页大小, 如"2MB", "1GB"
+    pub page_size: String,
+    pub limit: u64,
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LinuxNetwork {
+    #[serde(rename = "classID")]
+    pub class_id: Option<u32>,
+    pub priorities: Option<Vec<LinuxInterfacePriority>>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LinuxInterfacePriority {
+    pub name: String,
+    pub priority: u32,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
 #[serde(rename_all = "snake_case")]
 pub enum NamespaceType {
     Mount,
@@ -83,6 +311,7 @@ pub enum NamespaceType {
 pub struct Namespace {
     #[serde(rename = "type")]
     pub typ: NamespaceType,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub path: Option<String>,
 }
 
@@ -107,8 +336,39 @@ mod tests {
     use super::*;
     #[test]
     fn test_serde() {
-        let s = r#"{"oci_version":"1"}"#;
-        let v: Spec = serde_json::from_str(&s).unwrap();
+        let s = r#"{"ociVersion":"1"}"#;
+        let v: Spec = serde_json::from_str(s).unwrap();
         println!("{:?}", v);
     }
+
+    #[test]
+    fn test_has_limits() {
+        let mut resources: LinuxResources =
+            serde_json::from_str(r#"{"devices":[{"allow":false,"access":"rwm"}]}"#).unwrap();
+        assert!(!resources.has_limits());
+        resources.pids = Some(LinuxPids { limit: 10 });
+        assert!(resources.has_limits());
+    }
+
+    #[test]
+    fn test_process_path_env() {
+        let s = r#"{"args":["sh"],"env":["TERM=xterm","PATH=/usr/bin:/bin"],"cwd":"/"}"#;
+        let p: Process = serde_json::from_str(s).unwrap();
+        assert_eq!(p.path_env(), Some("/usr/bin:/bin"));
+    }
+
+    #[test]
+    fn test_resources_merge() {
+        let mut r: LinuxResources =
+            serde_json::from_str(r#"{"cpu":{"shares":1024,"quota":50000},"pids":{"limit":10}}"#)
+                .unwrap();
+        let update: LinuxResources =
+            serde_json::from_str(r#"{"cpu":{"quota":20000},"memory":{"limit":1048576}}"#).unwrap();
+        r.merge(&update).unwrap();
+        let cpu = r.cpu.unwrap();
+        assert_eq!(cpu.shares, Some(1024));
+        assert_eq!(cpu.quota, Some(20000));
+        assert_eq!(r.memory.unwrap().limit, Some(1048576));
+        assert_eq!(r.pids.unwrap().limit, 10);
+    }
 }
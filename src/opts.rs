@@ -1,11 +1,25 @@
-use crate::cli::{Create, Run, Start};
+use crate::cli::{
+    Create, Delete, Events, Exec, Kill, List, Pause, Ps, Resume, Run, Spec, Start, State, Update,
+};
 use crate::container::container::Container;
-use crate::utils::ipc::Reader;
-use anyhow::{Context, Result};
+use crate::container::events::EventsWatcher;
+use crate::container::exec::ExecContainer;
+use crate::container::rootless;
+use crate::oci::oci;
+use crate::oci::oci::{LinuxCpu, LinuxMemory, LinuxPids, LinuxResources, Process, User};
+use crate::utils::signal::parse_signal;
+use anyhow::{anyhow, bail, Context, Result};
+use chrono::SecondsFormat;
 use nix::sys::wait::waitpid;
+use nix::unistd::Pid;
+use serde::Serialize;
+use std::path::PathBuf;
+use std::process::Command;
 
-pub fn create(c: Create) -> Result<()> {
-    Container::new(c.container_id, c.bundle).create()?;
+pub fn create(c: Create, systemd_cgroup: bool) -> Result<()> {
+    Container::new(c.container_id, c.bundle)
+        .set_systemd_cgroup(systemd_cgroup)
+        .create()?;
     Ok(())
 }
 
@@ -15,9 +29,322 @@ pub fn start(c: Start) -> Result<()> {
     Ok(())
 }
 
-pub fn run(r: Run) -> Result<()> {
-    let (mut container, pid) = Container::new(r.container_id, r.bundle).create()?;
+pub fn run(r: Run, systemd_cgroup: bool) -> Result<()> {
+    let (mut container, pid) = Container::new(r.container_id, r.bundle)
+        .set_systemd_cgroup(systemd_cgroup)
+        .create()?;
     container.start()?;
     waitpid(pid, None)?;
     Ok(())
 }
+
+pub fn state(s: State) -> Result<()> {
+    let mut container = Container::load(s.container_id)?;
+    container.refresh_status()?;
+    println!(
+        "{}",
+        serde_json::to_string_pretty(&container.state.oci_state())?
+    );
+    Ok(())
+}
+
+pub fn kill(k: Kill) -> Result<()> {
+    let signal = parse_signal(&k.signal)?;
+    let mut container = Container::load(k.container_id)?;
+    container.kill(signal, k.all)
+}
+
+pub fn delete(d: Delete) -> Result<()> {
+    Container::delete(d.container_id, d.force)
+}
+
+pub fn pause(p: Pause) -> Result<()> {
+    Container::load(p.container_id)?.pause()
+}
+
+pub fn resume(r: Resume) -> Result<()> {
+    Container::load(r.container_id)?.resume()
+}
+
+#[derive(Serialize)]
+struct ContainerSummary {
+    id: String,
+    pid: i32,
+    status: String,
+    bundle: PathBuf,
+    created: String,
+    owner: String,
+}
+
+pub fn list(l: List) -> Result<()> {
+    let mut summaries = Vec::new();
+    for (id, container) in Container::list()? {
+        // 单个容器状态损坏时只报告错误, 不影响其他容器
+        let summary = container.and_then(|mut c| {
+            c.refresh_status()?;
+            Ok(ContainerSummary {
+                id: c.state.id.clone(),
+                pid: c.state.pid,
+                status: c.state.status.to_string(),
+                bundle: c.state.bundle.clone(),
+                created: c
+                    .state
+                    .created
+                    .map(|t| t.to_rfc3339_opts(SecondsFormat::Secs, true))
+                    .unwrap_or_default(),
+                owner: c.owner()?,
+            })
+        });
+        match summary {
+            Ok(summary) => summaries.push(summary),
+            Err(err) => eprintln!("failed to load container {}: {}", id, err),
+        }
+    }
+    if l.quiet {
+        for s in summaries.iter() {
+            println!("{}", s.id);
+        }
+        return Ok(());
+    }
+    if l.format == "json" {
+        println!("{}", serde_json::to_string_pretty(&summaries)?);
+        return Ok(());
+    }
+    let rows: Vec<[String; 6]> = summaries
+        .into_iter()
+        .map(|s| {
+            [
+                s.id,
+                s.pid.to_string(),
+                s.status,
+                s.bundle.display().to_string(),
+                s.created,
+                s.owner,
+            ]
+        })
+        .collect();
+    print_table(["ID", "PID", "STATUS", "BUNDLE", "CREATED", "OWNER"], &rows);
+    Ok(())
+}
+
+fn print_table<const N: usize>(header: [&str; N], rows: &[[String; N]]) {
+    let mut widths = header.map(|h| h.len());
+    for row in rows {
+        for (i, col) in row.iter().enumerate() {
+            widths[i] = widths[i].max(col.len());
+        }
+    }
+    let format_row = |cols: Vec<&str>| {
+        cols.iter()
+            .enumerate()
+            .map(|(i, c)| format!("{:<width$}", c, width = widths[i]))
+            .collect::<Vec<_>>()
+            .join("   ")
+            .trim_end()
+            .to_owned()
+    };
+    println!("{}", format_row(header.to_vec()));
+    for row in rows {
+        println!("{}", format_row(row.iter().map(|c| c.as_str()).collect()));
+    }
+}
+
+#[derive(Serialize)]
+struct ProcessSummary {
+    pid: i32,
+    cmdline: Vec<String>,
+}
+
+pub fn ps(p: Ps) -> Result<()> {
+    let container = Container::load(p.container_id)?;
+    let pids = container.pids()?;
+    if p.format == "json" {
+        let processes: Vec<ProcessSummary> = pids
+            .iter()
+            .map(|pid| ProcessSummary {
+                pid: pid.as_raw(),
+                // 读取过程中进程可能已经退出
+                cmdline: procfs::process::Process::new(pid.as_raw())
+                    .and_then(|p| p.cmdline())
+                    .unwrap_or_default(),
+            })
+            .collect();
+        println!("{}", serde_json::to_string_pretty(&processes)?);
+        return Ok(());
+    }
+    let ps_options = if p.ps_options.is_empty() {
+        vec!["-ef".to_owned()]
+    } else {
+        p.ps_options
+    };
+    let output = Command::new("ps")
+        .args(&ps_options)
+        .output()
+        .context("failed to run ps")?;
+    if !output.status.success() {
+        bail!(
+            "ps failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    for line in filter_ps_output(&String::from_utf8_lossy(&output.stdout), &pids)? {
+        println!("{}", line);
+    }
+    Ok(())
+}
+
+//保留ps输出的表头和PID列属于容器的行
+fn filter_ps_output<'a>(output: &'a str, pids: &[Pid]) -> Result<Vec<&'a str>> {
+    let mut lines = output.lines();
+    let header = lines.next().ok_or_else(|| anyhow!("ps output is empty"))?;
+    let pid_index = header
+        .split_whitespace()
+        .position(|field| field == "PID")
+        .ok_or_else(|| anyhow!("couldn't find PID field in ps output"))?;
+    let mut filtered = vec![header];
+    for line in lines {
+        let pid = line
+            .split_whitespace()
+            .nth(pid_index)
+            .and_then(|pid| pid.parse().ok())
+            .map(Pid::from_raw);
+        if pid.is_some_and(|pid| pids.contains(&pid)) {
+            filtered.push(line);
+        }
+    }
+    Ok(filtered)
+}
+
+pub fn events(e: Events) -> Result<()> {
+    let watcher = EventsWatcher::new(Container::load(e.container_id)?, e.interval)?;
+    if e.stats {
+        watcher.stats()
+    } else {
+        watcher.watch()
+    }
+}
+
+pub fn spec(s: Spec) -> Result<()> {
+    let spec = if s.rootless {
+        rootless::rootless_spec()?
+    } else {
+        oci::Spec::example()
+    };
+    let path = s.bundle.join("config.json");
+    let file = match std::fs::OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(&path)
+    {
+        Ok(file) => file,
+        Err(err) if err.kind() == std::io::ErrorKind::AlreadyExists => {
+            bail!("{:?} already exists, refusing to overwrite it", path)
+        }
+        Err(err) => return Err(err).with_context(|| format!("failed to create {:?}", path)),
+    };
+    serde_json::to_writer_pretty(file, &spec)?;
+    Ok(())
+}
+
+pub fn update(u: Update) -> Result<()> {
+    let mut resources = match u.resources {
+        Some(path) if path.as_os_str() == "-" => serde_json::from_reader(std::io::stdin())?,
+        Some(path) => LinuxResources::load(path)?,
+        None => LinuxResources::default(),
+    };
+    // 命令行参数优先于resources文件
+    let cpu = LinuxCpu {
+        shares: u.cpu_shares,
+        quota: u.cpu_quota,
+        period: u.cpu_period,
+        cpus: u.cpuset_cpus,
+        ..Default::default()
+    };
+    let flags = LinuxResources {
+        cpu: (cpu != LinuxCpu::default()).then_some(cpu),
+        memory: u.memory.map(|limit| LinuxMemory {
+            limit: Some(limit),
+            ..Default::default()
+        }),
+        pids: u.pids_limit.map(|limit| LinuxPids { limit }),
+        ..Default::default()
+    };
+    resources.merge(&flags)?;
+    if resources == LinuxResources::default() {
+        bail!("no resources to update");
+    }
+    Container::load(u.container_id)?.update(&resources)
+}
+
+pub fn exec(e: Exec) -> Result<i32> {
+    let container = Container::load(e.container_id)?;
+    let mut process = match e.process {
+        Some(path) => {
+            let mut process = Process::load(path)?;
+            process.terminal |= e.tty;
+            process
+        }
+        None => {
+            if e.command.is_empty() {
+                bail!("no command or --process given");
+            }
+            // 默认使用容器spec中process的env, cwd和user
+            let spec = container.load_spec()?;
+            let mut process = spec.process.context("no process in spec")?;
+            process.args = Some(e.command);
+            process.terminal = e.tty;
+            process
+        }
+    };
+    let env = process.env.get_or_insert_with(Vec::new);
+    for kv in e.env {
+        let key = kv.split('=').next().unwrap_or_default().to_owned();
+        env.retain(|v| v.split('=').next() != Some(key.as_str()));
+        env.push(kv);
+    }
+    if let Some(cwd) = e.cwd {
+        process.cwd = cwd;
+    }
+    if let Some(user) = e.user {
+        process.user = parse_user(&user)?;
+    }
+    ExecContainer::new(container, process, e.detach, e.pid_file).exec()
+}
+
+//解析uid[:gid]
+fn parse_user(user: &str) -> Result<User> {
+    let (uid, gid) = match user.split_once(':') {
+        Some((uid, gid)) => (uid, gid),
+        None => (user, "0"),
+    };
+    Ok(User {
+        uid: uid
+            .parse()
+            .with_context(|| format!("invalid uid {}", uid))?,
+        gid: gid
+            .parse()
+            .with_context(|| format!("invalid gid {}", gid))?,
+        additional_gids: None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    fn test_filter_ps_output() {
+        let output = "UID   PID  PPID  C STIME TTY   TIME     CMD
+root    1     0  0 10:00 ?     00:00:01 /sbin/init
+root  100     1  0 10:01 ?     00:00:00 sleep 100
+root  101   100  0 10:01 ?     00:00:00 sh
+";
+        let pids = [Pid::from_raw(100), Pid::from_raw(101)];
+        let lines = filter_ps_output(output, &pids).unwrap();
+        assert_eq!(lines.len(), 3);
+        assert!(lines[0].starts_with("UID"));
+        assert!(lines[1].ends_with("sleep 100"));
+        assert!(lines[2].ends_with("sh"));
+
+        assert!(filter_ps_output("USER COMMAND\n", &pids).is_err());
+    }
+}
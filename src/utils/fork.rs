@@ -1,9 +1,5 @@
-use crate::common::error::Error;
-use crate::oci::oci::Namespace;
 use anyhow::Result;
-use libc::c_int;
-use nix::sched::{clone, CloneFlags};
-use nix::sys::signal::Signal;
+use nix::errno::Errno;
 use nix::unistd::{fork, ForkResult, Pid};
 
 pub fn fork_child<F: FnOnce() -> Result<()>>(f: F) -> Result<Pid> {
@@ -20,3 +16,10 @@ pub fn fork_child<F: FnOnce() -> Result<()>>(f: F) -> Result<Pid> {
         }
     }
 }
+
+//设置为child subreaper, 孙进程的父进程退出后会被过继给当前进程
+pub fn set_child_subreaper() -> Result<()> {
+    let ret = unsafe { libc::prctl(libc::PR_SET_CHILD_SUBREAPER, 1, 0, 0, 0) };
+    Errno::result(ret)?;
+    Ok(())
+}
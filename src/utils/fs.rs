@@ -1,12 +1,9 @@
 use anyhow::Context;
 use anyhow::Result;
 use std::fs;
-use std::io;
+use std::os::unix::fs::PermissionsExt;
 use std::path::Path;
 use std::path::PathBuf;
-pub fn get_exe_path() -> Result<PathBuf, io::Error> {
-    std::env::current_exe()
-}
 
 pub fn create_dir_all<P: AsRef<Path>>(path: P) -> Result<()> {
     let path = path.as_ref();
@@ -18,12 +15,37 @@ pub fn remove_dir_all<P: AsRef<Path>>(path: P) -> Result<()> {
     fs::remove_dir_all(path).with_context(|| format!("failed to remove directory {:?}", path))
 }
 
+fn is_executable(path: &Path) -> bool {
+    match fs::metadata(path) {
+        Ok(m) => m.is_file() && m.permissions().mode() & 0o111 != 0,
+        Err(_) => false,
+    }
+}
+
+//在PATH中查找可执行文件, 名字中带有/时直接使用
+pub fn find_executable(name: &str, path_env: &str) -> Result<PathBuf> {
+    if name.contains('/') {
+        return Ok(PathBuf::from(name));
+    }
+    path_env
+        .split(':')
+        .filter(|dir| !dir.is_empty())
+        .map(|dir| Path::new(dir).join(name))
+        .find(|p| is_executable(p))
+        .with_context(|| format!("executable {} not found in PATH {}", name, path_env))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     #[test]
-    fn test_get_exe_path() {
-        let path = get_exe_path();
-        println!("{:?}", path);
+    fn test_find_executable() {
+        let path = find_executable("sh", "/nonexistent:/bin:/usr/bin").unwrap();
+        assert!(path.ends_with("sh"));
+        assert_eq!(
+            find_executable("./sh", "/bin").unwrap(),
+            PathBuf::from("./sh")
+        );
+        assert!(find_executable("no-such-binary", "/bin:/usr/bin").is_err());
     }
 }
@@ -1,9 +1,8 @@
-use crate::common::error::Error;
 use anyhow::bail;
 use anyhow::Result;
 use nix::{
-    sys::{socket, uio},
-    unistd::{self, close, read, write},
+    sys::socket,
+    unistd::{close, read, write},
 };
 
 use serde::Serialize;
@@ -65,13 +64,15 @@ where
 {
     pub fn peek(&self) -> Result<usize> {
         let mut buf = [0u8; 4];
-        let _ = read(self.fd, &mut buf)?;
+        if read(self.fd, &mut buf)? == 0 {
+            bail!("ipc channel closed by peer");
+        }
         Ok(read_u32(&buf) as usize)
     }
 
     pub fn read(&self) -> Result<T> {
         let size = self.peek()?;
-        let mut buf = vec![0u8; size as usize];
+        let mut buf = vec![0u8; size];
         let num = read(self.fd, &mut buf)?;
         Ok(serde_json::from_slice(&buf[..num])?)
     }
@@ -105,7 +106,6 @@ where
 
 pub struct NotifyListener {
     fd: RawFd,
-    socket_path: PathBuf,
 }
 
 impl NotifyListener {
@@ -119,10 +119,7 @@ impl NotifyListener {
         let sockaddr = socket::SockAddr::new_unix(socket_path)?;
         socket::bind(raw_fd, &sockaddr)?;
         socket::listen(raw_fd, 10)?;
-        Ok(NotifyListener {
-            fd: raw_fd,
-            socket_path: socket_path.to_path_buf(),
-        })
+        Ok(NotifyListener { fd: raw_fd })
     }
 
     //
@@ -188,7 +185,7 @@ mod tests {
     fn test_ipc() {
         let (w, r) = new::<String>().unwrap();
         let _ = std::thread::spawn(move || {
-            std::thread::sleep_ms(1000);
+            std::thread::sleep(std::time::Duration::from_millis(1000));
             w.write("aa".to_owned()).unwrap();
             w.close().unwrap();
         });
@@ -203,7 +200,7 @@ mod tests {
         let socket_path = Path::new("/opt/test.sock");
         let notify_listener = NotifyListener::new(socket_path).unwrap();
         let _ = std::thread::spawn(move || {
-            std::thread::sleep_ms(1000);
+            std::thread::sleep(std::time::Duration::from_millis(1000));
             let notify_socket = NotifySocket::new(socket_path).unwrap();
             notify_socket.notify("start").unwrap();
             notify_socket.close().unwrap();
@@ -1,3 +1,5 @@
 pub mod fork;
 pub mod fs;
 pub mod ipc;
+pub mod signal;
+pub mod tty;
@@ -0,0 +1,32 @@
+use anyhow::{Context, Result};
+use nix::sys::signal::Signal;
+use std::convert::TryFrom;
+use std::str::FromStr;
+
+//解析信号, 支持数字(9), 带SIG前缀(SIGKILL)和不带前缀(kill)的名字
+pub fn parse_signal(signal: &str) -> Result<Signal> {
+    if let Ok(num) = signal.parse::<i32>() {
+        return Signal::try_from(num).with_context(|| format!("invalid signal {}", signal));
+    }
+    let name = signal.to_ascii_uppercase();
+    let name = if name.starts_with("SIG") {
+        name
+    } else {
+        format!("SIG{}", name)
+    };
+    Signal::from_str(&name).with_context(|| format!("invalid signal {}", signal))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    fn test_parse_signal() {
+        assert_eq!(parse_signal("9").unwrap(), Signal::SIGKILL);
+        assert_eq!(parse_signal("TERM").unwrap(), Signal::SIGTERM);
+        assert_eq!(parse_signal("SIGKILL").unwrap(), Signal::SIGKILL);
+        assert_eq!(parse_signal("hup").unwrap(), Signal::SIGHUP);
+        assert!(parse_signal("NOSUCH").is_err());
+        assert!(parse_signal("0").is_err());
+    }
+}
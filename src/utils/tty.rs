@@ -0,0 +1,93 @@
+use anyhow::{bail, Result};
+use nix::errno::Errno;
+use nix::poll::{poll, PollFd, PollFlags};
+use nix::sys::termios::{cfmakeraw, tcgetattr, tcsetattr, SetArg};
+use nix::unistd::{close, dup2, read, setsid, write};
+use std::os::unix::io::RawFd;
+
+const STDIN: RawFd = 0;
+const STDOUT: RawFd = 1;
+
+//在子进程中把pty slave设置为控制终端和标准输入输出
+pub fn setup_slave(slave: RawFd) -> Result<()> {
+    setsid()?;
+    if unsafe { libc::ioctl(slave, libc::TIOCSCTTY, 0) } < 0 {
+        bail!("failed to set controlling terminal: {}", Errno::last());
+    }
+    for fd in 0..3 {
+        dup2(slave, fd)?;
+    }
+    if slave > 2 {
+        close(slave)?;
+    }
+    Ok(())
+}
+
+//在当前终端和pty master之间转发数据, 直到pty的另一端全部关闭
+pub fn proxy(master: RawFd) -> Result<()> {
+    let orig = tcgetattr(STDIN).ok();
+    if let Some(termios) = &orig {
+        copy_winsize(STDIN, master);
+        let mut raw = termios.clone();
+        cfmakeraw(&mut raw);
+        tcsetattr(STDIN, SetArg::TCSANOW, &raw)?;
+    }
+    let ret = copy_loop(master);
+    if let Some(termios) = &orig {
+        tcsetattr(STDIN, SetArg::TCSANOW, termios)?;
+    }
+    ret
+}
+
+fn copy_winsize(from: RawFd, to: RawFd) {
+    let mut ws: libc::winsize = unsafe { std::mem::zeroed() };
+    unsafe {
+        if libc::ioctl(from, libc::TIOCGWINSZ, &mut ws) == 0 {
+            libc::ioctl(to, libc::TIOCSWINSZ, &ws);
+        }
+    }
+}
+
+fn copy_loop(master: RawFd) -> Result<()> {
+    let mut buf = [0u8; 4096];
+    let mut stdin_open = true;
+    loop {
+        let mut fds = vec![PollFd::new(master, PollFlags::POLLIN)];
+        if stdin_open {
+            fds.push(PollFd::new(STDIN, PollFlags::POLLIN));
+        }
+        match poll(&mut fds, -1) {
+            Ok(_) => {}
+            Err(Errno::EINTR) => continue,
+            Err(err) => return Err(err.into()),
+        }
+        if fds[0].revents().is_some_and(|r| !r.is_empty()) {
+            match read(master, &mut buf) {
+                // slave全部关闭后读master返回EIO
+                Ok(0) | Err(Errno::EIO) => return Ok(()),
+                Ok(n) => write_all(STDOUT, &buf[..n])?,
+                Err(Errno::EINTR) => {}
+                Err(err) => return Err(err.into()),
+            }
+        }
+        if stdin_open && fds[1].revents().is_some_and(|r| !r.is_empty()) {
+            match read(STDIN, &mut buf) {
+                Ok(0) => stdin_open = false,
+                Ok(n) => write_all(master, &buf[..n])?,
+                Err(Errno::EINTR) => {}
+                Err(_) => stdin_open = false,
+            }
+        }
+    }
+}
+
+fn write_all(fd: RawFd, mut buf: &[u8]) -> Result<()> {
+    while !buf.is_empty() {
+        match write(fd, buf) {
+            Ok(n) => buf = &buf[n..],
+            Err(Errno::EINTR) => {}
+            Err(err) => return Err(err.into()),
+        }
+    }
+    Ok(())
+}